@@ -0,0 +1,598 @@
+// Library for implementing the display
+use std::fmt;
+// Libraries for the ordering and the operator traits
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Arbitrary precision integer, stored as sign and magnitude.
+// The magnitude holds base 2^32 digits with the least significant digit first and no leading zeros,
+// zero is the empty magnitude and never negative
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt { negative: false, magnitude: Vec::new() }
+    }
+
+    pub fn one() -> Self {
+        BigInt::from(1u64)
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        let negative = negative && !magnitude.is_empty();
+        BigInt { negative, magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt { negative: false, magnitude: self.magnitude.clone() }
+    }
+
+    // Number of bits of the magnitude, zero has no bits
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => (self.magnitude.len() as u64 - 1) * 32 + (32 - top.leading_zeros() as u64),
+            None => 0,
+        }
+    }
+
+    // Parses an optionally signed string of decimal digits
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut magnitude = Vec::new();
+        // Nine decimal digits at a time always fit into one u32 digit
+        for chunk in digits.as_bytes().chunks(9) {
+            let chunk = std::str::from_utf8(chunk).ok()?;
+            let factor = 10u32.pow(chunk.len() as u32);
+            mul_small(&mut magnitude, factor);
+            add_small(&mut magnitude, chunk.parse().ok()?);
+        }
+        Some(BigInt::from_parts(negative, magnitude))
+    }
+
+    // Parses unsigned digits in base 2 to 16, like the "ff" of "0xff"
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        let mut magnitude = Vec::new();
+        for c in digits.chars() {
+            mul_small(&mut magnitude, radix);
+            add_small(&mut magnitude, c.to_digit(radix)?);
+        }
+        Some(BigInt::from_parts(false, magnitude))
+    }
+
+    // Digits in base 2 to 16 without prefix, lowercase letters and a leading '-' for negative numbers
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        let mut magnitude = self.magnitude.clone();
+        let mut digits = Vec::new();
+        while !magnitude.is_empty() {
+            digits.push(std::char::from_digit(div_small(&mut magnitude, radix), radix).unwrap());
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+        if digits.is_empty() {
+            digits.push('0');
+        }
+        if self.negative {
+            digits.push('-');
+        }
+        digits.iter().rev().collect()
+    }
+
+    // Integer value of a float, None for fractions, infinity and NaN
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+        // Split the float into mantissa and binary exponent, subnormal numbers have no implicit bit
+        let bits = value.abs().to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = match biased_exponent {
+            0 => (fraction, -1074),
+            _ => (fraction | (1 << 52), biased_exponent - 1075),
+        };
+        let magnitude = BigInt::from(mantissa);
+        let magnitude = if exponent >= 0 {
+            magnitude.shl(exponent as u64)
+        } else {
+            magnitude.shr((-exponent) as u64)
+        };
+        Some(if value < 0.0 { -magnitude } else { magnitude })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let mut result = 0.0;
+        for digit in self.magnitude.iter().rev() {
+            result = result * 4294967296.0 + *digit as f64;
+        }
+        if self.negative { -result } else { result }
+    }
+
+    // Value as u64 if it is non-negative and small enough
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.negative || self.magnitude.len() > 2 {
+            return None;
+        }
+        Some(self.magnitude.iter().rev().fold(0u64, |acc, digit| (acc << 32) | *digit as u64))
+    }
+
+    pub fn shl(&self, shift: u64) -> Self {
+        let words = (shift / 32) as usize;
+        let bits = (shift % 32) as u32;
+        let mut magnitude = vec![0; words];
+        let mut carry = 0u32;
+        for digit in &self.magnitude {
+            if bits == 0 {
+                magnitude.push(*digit);
+            } else {
+                magnitude.push((digit << bits) | carry);
+                carry = digit >> (32 - bits);
+            }
+        }
+        magnitude.push(carry);
+        BigInt::from_parts(self.negative, magnitude)
+    }
+
+    // Shifts the magnitude to the right, so negative numbers are rounded towards zero
+    pub fn shr(&self, shift: u64) -> Self {
+        let words = (shift / 32) as usize;
+        let bits = (shift % 32) as u32;
+        if words >= self.magnitude.len() {
+            return BigInt::zero();
+        }
+        let source = &self.magnitude[words..];
+        let mut magnitude = Vec::with_capacity(source.len());
+        for (index, digit) in source.iter().enumerate() {
+            if bits == 0 {
+                magnitude.push(*digit);
+            } else {
+                let high = source.get(index + 1).map_or(0, |next| next << (32 - bits));
+                magnitude.push((digit >> bits) | high);
+            }
+        }
+        BigInt::from_parts(self.negative, magnitude)
+    }
+
+    // Truncating division with remainder, the remainder has the sign of the dividend.
+    // Returns None when dividing by zero
+    pub fn div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &divisor.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != divisor.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exponent: u64) -> Self {
+        let mut base = self.clone();
+        let mut result = BigInt::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    // Greatest common divisor of the magnitudes, the binary algorithm only shifts and subtracts
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        if a.is_zero() {
+            return b;
+        }
+        if b.is_zero() {
+            return a;
+        }
+        let shift = a.trailing_zeros().min(b.trailing_zeros());
+        a = a.shr(a.trailing_zeros());
+        loop {
+            b = b.shr(b.trailing_zeros());
+            if a > b {
+                std::mem::swap(&mut a, &mut b);
+            }
+            b = &b - &a;
+            if b.is_zero() {
+                return a.shl(shift);
+            }
+        }
+    }
+
+    fn trailing_zeros(&self) -> u64 {
+        let mut zeros = 0;
+        for digit in &self.magnitude {
+            if *digit == 0 {
+                zeros += 32;
+            } else {
+                return zeros + digit.trailing_zeros() as u64;
+            }
+        }
+        zeros
+    }
+
+    // Exact square root of a non-negative perfect square, None otherwise
+    pub fn exact_sqrt(&self) -> Option<BigInt> {
+        if self.negative {
+            return None;
+        }
+        if self.is_zero() {
+            return Some(BigInt::zero());
+        }
+        // Newton iteration starting above the root, it decreases until it reaches floor(sqrt)
+        let mut root = BigInt::one().shl(self.bits().div_ceil(2));
+        loop {
+            let (quotient, _) = self.div_rem(&root)?;
+            let next = (&root + &quotient).shr(1);
+            if next >= root {
+                break;
+            }
+            root = next;
+        }
+        if &root * &root == *self { Some(root) } else { None }
+    }
+}
+
+// Multiplies the magnitude in place by a single digit
+fn mul_small(magnitude: &mut Vec<u32>, factor: u32) {
+    let mut carry = 0u64;
+    for digit in magnitude.iter_mut() {
+        let product = *digit as u64 * factor as u64 + carry;
+        *digit = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+// Adds a single digit to the magnitude in place
+fn add_small(magnitude: &mut Vec<u32>, value: u32) {
+    let mut carry = value as u64;
+    for digit in magnitude.iter_mut() {
+        if carry == 0 {
+            return;
+        }
+        let sum = *digit as u64 + carry;
+        *digit = sum as u32;
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+// Divides the magnitude in place by a single digit and returns the remainder
+fn div_small(magnitude: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in magnitude.iter_mut().rev() {
+        let current = (remainder << 32) | *digit as u64;
+        *digit = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    remainder as u32
+}
+
+fn compare_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (index, digit) in long.iter().enumerate() {
+        let sum = *digit as u64 + *short.get(index).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+    result
+}
+
+// Subtracts b from a, a has to be at least as large as b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (index, digit) in a.iter().enumerate() {
+        let mut difference = *digit as i64 - *b.get(index).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let current = result[i + j] as u64 + *x as u64 * *y as u64 + carry;
+            result[i + j] = current as u32;
+            carry = current >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+// Schoolbook long division (Knuth, algorithm D), the divisor must not be zero
+fn div_rem_magnitude(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if compare_magnitude(dividend, divisor) == Ordering::Less {
+        return (Vec::new(), dividend.to_vec());
+    }
+    if divisor.len() == 1 {
+        let mut quotient = dividend.to_vec();
+        let remainder = div_small(&mut quotient, divisor[0]);
+        return (quotient, vec![remainder]);
+    }
+
+    // Normalize so the top digit of the divisor has its highest bit set
+    let shift = divisor.last().unwrap().leading_zeros();
+    let divisor = BigInt::from_parts(false, divisor.to_vec()).shl(shift as u64).magnitude;
+    let mut remainder = BigInt::from_parts(false, dividend.to_vec()).shl(shift as u64).magnitude;
+    remainder.push(0);
+
+    let n = divisor.len();
+    let m = remainder.len() - n - 1;
+    let top = divisor[n - 1] as u64;
+    let second = divisor[n - 2] as u64;
+    let mut quotient = vec![0u32; m + 1];
+
+    for j in (0..=m).rev() {
+        // Estimate the quotient digit from the top two digits and correct it at most twice
+        let numerator = ((remainder[j + n] as u64) << 32) | remainder[j + n - 1] as u64;
+        let mut estimate = numerator / top;
+        let mut rest = numerator % top;
+        while estimate >= 1 << 32 || estimate * second > ((rest << 32) | remainder[j + n - 2] as u64) {
+            estimate -= 1;
+            rest += top;
+            if rest >= 1 << 32 {
+                break;
+            }
+        }
+
+        // Multiply and subtract, adding back once if the estimate was still one too large
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = estimate * divisor[i] as u64 + carry;
+            carry = product >> 32;
+            let difference = remainder[i + j] as i64 - (product & 0xffff_ffff) as i64 - borrow;
+            remainder[i + j] = difference as u32;
+            borrow = if difference < 0 { 1 } else { 0 };
+        }
+        let difference = remainder[j + n] as i64 - carry as i64 - borrow;
+        remainder[j + n] = difference as u32;
+
+        if difference < 0 {
+            estimate -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = remainder[i + j] as u64 + divisor[i] as u64 + carry;
+                remainder[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            remainder[j + n] = remainder[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = estimate as u32;
+    }
+
+    remainder.truncate(n);
+    let remainder = BigInt::from_parts(false, remainder).shr(shift as u64).magnitude;
+    (quotient, remainder)
+}
+
+impl From<u64> for BigInt {
+    fn from(value: u64) -> Self {
+        BigInt::from_parts(false, vec![value as u32, (value >> 32) as u32])
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = BigInt::from(value.unsigned_abs()).magnitude;
+        BigInt::from_parts(value < 0, magnitude)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+        }
+        // Different signs: subtract the smaller magnitude from the larger one
+        match compare_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => {
+                BigInt::from_parts(other.negative, sub_magnitude(&other.magnitude, &self.magnitude))
+            }
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other.clone()
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let magnitude = mul_magnitude(&self.magnitude, &other.magnitude);
+        BigInt::from_parts(self.negative != other.negative, magnitude)
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Split off nine decimal digits at a time, starting with the least significant ones
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = Vec::new();
+        while !magnitude.is_empty() {
+            chunks.push(div_small(&mut magnitude, 1_000_000_000));
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        text.push_str(&chunks.pop().unwrap().to_string());
+        for chunk in chunks.iter().rev() {
+            text.push_str(&format!("{:09}", chunk));
+        }
+        f.pad(&text)
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(big("0").to_string(), "0");
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("+42").to_string(), "42");
+        assert_eq!(big("-123456789012345678901234567890").to_string(), "-123456789012345678901234567890");
+        assert_eq!(big("1000000000").to_string(), "1000000000");
+        assert_eq!(BigInt::parse("12a"), None);
+        assert_eq!(BigInt::parse("-"), None);
+    }
+
+    #[test]
+    fn test_radix() {
+        assert_eq!(BigInt::parse_radix("ff", 16), Some(big("255")));
+        assert_eq!(BigInt::parse_radix("1010", 2), Some(big("10")));
+        assert_eq!(BigInt::parse_radix("ffffffffffffffffffff", 16).unwrap().to_string(), "1208925819614629174706175");
+        assert_eq!(BigInt::parse_radix("12", 2), None);
+        assert_eq!(BigInt::parse_radix("", 8), None);
+        assert_eq!(big("-255").to_string_radix(16), "-ff");
+        assert_eq!(big("1208925819614629174706175").to_string_radix(16), "ffffffffffffffffffff");
+        assert_eq!(BigInt::zero().to_string_radix(2), "0");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = big("99999999999999999999");
+        let b = big("-12345678901234567890");
+        assert_eq!((&a + &b).to_string(), "87654321098765432109");
+        assert_eq!((&b - &a).to_string(), "-112345678901234567889");
+        assert_eq!((&a * &b).to_string(), "-1234567890123456788987654321098765432110");
+        assert_eq!(big("2").pow(100).to_string(), "1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let a = big("1267650600228229401496703205377");
+        let (q, r) = a.div_rem(&big("-4294967297")).unwrap();
+        assert_eq!(q.to_string(), "-295147905110633349135");
+        assert_eq!(r.to_string(), "4294967282");
+        assert_eq!(&(&q * &big("-4294967297")) + &r, a);
+        assert_eq!(a.div_rem(&BigInt::zero()), None);
+
+        let big_divisor = big("340282366920938463463374607431768211457");
+        let product = &big_divisor * &big("98765432109876543210");
+        let (q, r) = (&product + &big("12345")).div_rem(&big_divisor).unwrap();
+        assert_eq!(q.to_string(), "98765432109876543210");
+        assert_eq!(r.to_string(), "12345");
+    }
+
+    #[test]
+    fn test_gcd_and_sqrt() {
+        assert_eq!(big("-48").gcd(&big("180")).to_string(), "12");
+        assert_eq!(big("0").gcd(&big("7")).to_string(), "7");
+        let square = big("123456789123456789").pow(2);
+        assert_eq!(square.exact_sqrt(), Some(big("123456789123456789")));
+        assert_eq!((&square + &BigInt::one()).exact_sqrt(), None);
+        assert_eq!(big("-4").exact_sqrt(), None);
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(BigInt::from_f64(1e20).unwrap().to_string(), "100000000000000000000");
+        assert_eq!(BigInt::from_f64(-3.0).unwrap().to_string(), "-3");
+        assert_eq!(BigInt::from_f64(0.5), None);
+        assert_eq!(big("-1267650600228229401496703205376").to_f64(), -2f64.powi(100));
+        assert_eq!(big("12345").to_u64(), Some(12345));
+        assert_eq!(big("-1").to_u64(), None);
+        assert_eq!(big("5").shl(40).shr(39).to_string(), "10");
+    }
+}
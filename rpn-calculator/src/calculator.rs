@@ -0,0 +1,2348 @@
+use std::cmp::Ordering;
+// Sorted map for the variables, so listing them is stable
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::bigint::BigInt;
+use crate::complex::Complex;
+use crate::error::CalcError;
+use crate::expression::Expr;
+use crate::format::{self, NumberFormat, Radix};
+use crate::matrix;
+use crate::operators::{Arity, Evaluation, Function, Operator, Operators};
+use crate::parser;
+use crate::plot::Plot;
+use crate::rational::Rational;
+use crate::statistics;
+use crate::symbolic;
+use crate::units::{self, Unit};
+use crate::value::Value;
+
+// Class declaration, clone trait
+#[derive(Clone)]
+pub struct RPNCalculator {
+    stack: Vec<StackEntry>,
+    variables: BTreeMap<String, Value>,
+    definitions: BTreeMap<String, Vec<String>>,
+    mode: NumberMode,
+    angle_mode: AngleMode,
+    radix: Radix,
+    number_format: NumberFormat,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    // Snapshot of the token that is running, filled in before the token changes the state
+    pending: Snapshot,
+    // Changes of the undo history during the current line, None outside of evaluate_line
+    line_changes: Option<Vec<HistoryChange>>,
+    // Shared with the calculators that run parts of a calculation, like the body of a config operator
+    operators: Rc<Operators>,
+}
+
+// A stack slot: the value together with the expression that produced it
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackEntry {
+    pub value: Value,
+    pub expr: Expr,
+}
+
+// How new numbers are entered: as floats or as exact integers and fractions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberMode {
+    Float,
+    Exact,
+}
+
+// Unit of the angles taken by sin, cos and tan and returned by their inverse functions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AngleMode {
+    Radians,
+    Degrees,
+}
+
+// Every word the calculator knows besides the operators of the registry and user definitions
+const BUILTIN_WORDS: &[&str] = &[
+    "sto", "rcl", "undo", "redo", "exact", "float", "deg", "rad", "dec", "hex", "oct", "bin", "std", "fix", "sci",
+    "eng", "to", "x", "at",
+];
+
+// Largest factorial calculated exactly, the result already has more than 35000 digits
+const MAX_EXACT_FACTORIAL: u64 = 10_000;
+
+// Exact powers are only calculated up to this many bits, larger ones fall back to floating point
+const MAX_EXACT_POWER_BITS: u64 = 1 << 20;
+
+// State before an accepted token, used for rolling back errors and for undo/redo. Only what the token
+// changed is kept: the stack entries from 'kept' on, and the variables if it stored one
+#[derive(Clone, Default)]
+struct Snapshot {
+    kept: usize,
+    entries: Vec<StackEntry>,
+    variables: Option<BTreeMap<String, Value>>,
+}
+
+// A change of the undo history, reverted in reverse order if a later token of the line fails
+#[derive(Clone)]
+enum HistoryChange {
+    // An accepted token, with the redo entries it made unreachable
+    Accepted(Vec<Snapshot>),
+    Undone,
+    Redone,
+}
+
+impl Default for RPNCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RPNCalculator {
+    // Constructor, initializing the vectors
+    pub fn new() -> Self {
+        Self::with_operators(Rc::new(Operators::default()))
+    }
+
+    // Calculator with the given operators, e.g. the built-in ones and those of a config file
+    pub fn with_operators(operators: Rc<Operators>) -> Self {
+        Self {
+            stack: Vec::new(),
+            variables: BTreeMap::new(),
+            definitions: BTreeMap::new(),
+            mode: NumberMode::Float,
+            angle_mode: AngleMode::Radians,
+            radix: Radix::Decimal,
+            number_format: NumberFormat::Standard,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            pending: Snapshot::default(),
+            line_changes: None,
+            operators,
+        }
+    }
+
+    // Adds the operators of a config file, see Operators::load
+    pub fn load_operators(&mut self, text: &str) -> Result<Vec<String>, CalcError> {
+        Rc::make_mut(&mut self.operators).load(text)
+    }
+
+    pub fn operators(&self) -> &Operators {
+        &self.operators
+    }
+
+    // Words that take operands, like '+', 'dup' or the operators of a config file
+    pub fn is_operator(&self, word: &str) -> bool {
+        self.operators.is_operator(word)
+    }
+
+    // Applying operation to the stack, rolling back the stack and the variables if it fails
+    pub fn apply_operation(&mut self, token: &str) -> Result<(), CalcError> {
+        match token {
+            "undo" => return self.undo().map(|()| self.record(HistoryChange::Undone)),
+            "redo" => return self.redo().map(|()| self.record(HistoryChange::Redone)),
+            "exact" => return self.set_mode(NumberMode::Exact),
+            "float" => return self.set_mode(NumberMode::Float),
+            "deg" => return self.set_angle_mode(AngleMode::Degrees),
+            "rad" => return self.set_angle_mode(AngleMode::Radians),
+            "dec" | "hex" | "oct" | "bin" => return self.set_radix(Radix::from_name(token).unwrap()),
+            "std" => return self.set_number_format(NumberFormat::Standard),
+            _ => {}
+        }
+        // Literals are kept without whitespace, so "[1 2]" and "[1,2]" are the same token
+        let literal = token.starts_with('[').then(|| matrix::canonical(token)).flatten();
+        let token = literal.as_deref().unwrap_or(token);
+        self.pending = Snapshot { kept: self.stack.len(), ..Snapshot::default() };
+        let result = self.execute_token(token);
+        let snapshot = std::mem::take(&mut self.pending);
+
+        match result {
+            Ok(()) => {
+                // A new entry makes the previously undone entries unreachable
+                self.undo_stack.push(snapshot);
+                let unreachable = std::mem::take(&mut self.redo_stack);
+                self.record(HistoryChange::Accepted(unreachable));
+            }
+            Err(_) => {
+                self.swap_state(snapshot);
+            }
+        }
+        result
+    }
+
+    // Dispatches a single token, user defined words are expanded into their body
+    fn execute_token(&mut self, token: &str) -> Result<(), CalcError> {
+        if let Some(body) = self.definitions.get(token).cloned() {
+            return body.iter().try_for_each(|word| self.execute_token(word));
+        }
+
+        let symbolic_top = self.stack.last().is_some_and(|entry| entry.value.is_symbolic());
+        let operators = Rc::clone(&self.operators);
+        match token {
+            _ if symbolic_top && operators.get(token).is_some_and(|operator| operator.derivative.is_some()) => {
+                self.symbolic_function_handling(token)
+            }
+            "x" => self.symbol_handling(),
+            _ if token.starts_with('=') => self.store_variable_handling(&token[1..]),
+            _ if token.starts_with('$') => self.recall_variable_handling(&token[1..]),
+            _ if token.starts_with("->") => self.conversion_handling(&token[2..]),
+            _ if token.starts_with('@') => self.evaluation_handling(&token[1..]),
+            // Operators come before the units, so 'min' is the minimum and not minutes
+            _ => match operators.get(token) {
+                Some(operator) => {
+                    self.check_operands(token, operator)?;
+                    match (operator.arity, &operator.evaluation) {
+                        (arity, Evaluation::Function(function)) => {
+                            self.function_handling(token, self.operand_count(arity), *function)
+                        }
+                        (_, Evaluation::Builtin(handler) | Evaluation::Word(handler)) => handler(self, token),
+                        (Arity::Fixed(arity), Evaluation::Rpn(body)) => {
+                            self.custom_operator_handling(token, arity, body)
+                        }
+                        _ => Err(CalcError::UnknownToken(token.to_owned())),
+                    }
+                }
+                None if Unit::parse(token).is_some() => self.unit_handling(token),
+                None => self.new_number_handling(token),
+            },
+        }
+    }
+
+    // Restores the state before the last accepted token
+    pub fn undo(&mut self) -> Result<(), CalcError> {
+        let snapshot = self.undo_stack.pop().ok_or(CalcError::NothingToUndo)?;
+        let current = self.swap_state(snapshot);
+        self.redo_stack.push(current);
+        Ok(())
+    }
+
+    // Reapplies the last undone token
+    pub fn redo(&mut self) -> Result<(), CalcError> {
+        let snapshot = self.redo_stack.pop().ok_or(CalcError::NothingToRedo)?;
+        let current = self.swap_state(snapshot);
+        self.undo_stack.push(current);
+        Ok(())
+    }
+
+    // Selects how numbers are entered from now on, values already on the stack keep their form.
+    // Operations on exact values stay exact as long as the result is rational
+    pub fn set_mode(&mut self, mode: NumberMode) -> Result<(), CalcError> {
+        self.mode = mode;
+        Ok(())
+    }
+
+    pub fn mode(&self) -> NumberMode {
+        self.mode
+    }
+
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) -> Result<(), CalcError> {
+        self.angle_mode = angle_mode;
+        Ok(())
+    }
+
+    pub fn angle_mode(&self) -> AngleMode {
+        self.angle_mode
+    }
+
+    // Selects the base integers are printed in, input in every base is always accepted
+    pub fn set_radix(&mut self, radix: Radix) -> Result<(), CalcError> {
+        self.radix = radix;
+        Ok(())
+    }
+
+    pub fn radix(&self) -> Radix {
+        self.radix
+    }
+
+    // Selects how many digits are printed, the values keep their full precision
+    pub fn set_number_format(&mut self, number_format: NumberFormat) -> Result<(), CalcError> {
+        if number_format.digits() > format::MAX_DIGITS {
+            return Err(CalcError::DomainError(format!(
+                "at most {} digits can be shown, got {}",
+                format::MAX_DIGITS,
+                number_format.digits()
+            )));
+        }
+        self.number_format = number_format;
+        Ok(())
+    }
+
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+
+    // A value as it is printed with the current base and number format
+    pub fn format_value(&self, value: &Value) -> String {
+        format::format_value(value, self.radix, self.number_format)
+    }
+
+    fn record(&mut self, change: HistoryChange) {
+        if let Some(changes) = &mut self.line_changes {
+            changes.push(change);
+        }
+    }
+
+    // Undo and redo are each other's inverse, an accepted token goes back to the state before it
+    fn revert(&mut self, change: HistoryChange) {
+        match change {
+            HistoryChange::Accepted(unreachable) => {
+                let snapshot = self.undo_stack.pop().unwrap();
+                self.swap_state(snapshot);
+                self.redo_stack = unreachable;
+            }
+            HistoryChange::Undone => self.redo().unwrap(),
+            HistoryChange::Redone => self.undo().unwrap(),
+        }
+    }
+
+    // Puts back the state of a snapshot and returns the snapshot of the state it replaced
+    fn swap_state(&mut self, snapshot: Snapshot) -> Snapshot {
+        let entries = self.stack.split_off(snapshot.kept);
+        self.stack.extend(snapshot.entries);
+        let variables = snapshot.variables.map(|variables| std::mem::replace(&mut self.variables, variables));
+        Snapshot { kept: snapshot.kept, entries, variables }
+    }
+
+    // Keeps the entries from the index on for the snapshot of the running token, before it changes them
+    fn save_from(&mut self, index: usize) {
+        let pending = &mut self.pending;
+        if index < pending.kept {
+            let mut entries = self.stack[index..pending.kept].to_vec();
+            entries.append(&mut pending.entries);
+            pending.entries = entries;
+            pending.kept = index;
+        }
+    }
+
+    fn save_variables(&mut self) {
+        if self.pending.variables.is_none() {
+            self.pending.variables = Some(self.variables.clone());
+        }
+    }
+
+    fn pop_entry(&mut self) -> StackEntry {
+        self.save_from(self.stack.len() - 1);
+        self.stack.pop().unwrap()
+    }
+
+    // Checks that enough values are on the stack for the given token
+    fn require_operands(&self, token: &str, required: usize) -> Result<(), CalcError> {
+        if self.stack.len() < required {
+            return Err(CalcError::StackUnderflow {
+                token: token.to_owned(),
+                required,
+                available: self.stack.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // Number of operands an operator takes, all of them for the statistics
+    fn operand_count(&self, arity: Arity) -> usize {
+        match arity {
+            Arity::Fixed(arity) => arity,
+            Arity::Stack(minimum) => self.stack.len().max(minimum),
+        }
+    }
+
+    // Operands of a kind the operator does not take are rejected before it runs. If operands are
+    // missing, the operator reports that itself
+    fn check_operands(&self, token: &str, operator: &Operator) -> Result<(), CalcError> {
+        let count = self.operand_count(operator.arity);
+        let Some(start) = self.stack.len().checked_sub(count) else {
+            return Ok(());
+        };
+        match self.stack[start..].iter().find(|entry| !operator.accepts.contains(&entry.value)) {
+            Some(entry) => Err(not_real(token, &entry.value)),
+            None => Ok(()),
+        }
+    }
+
+    // Takes the operands of a token from the stack, the deepest operand comes first
+    fn pop_operands(&mut self, token: &str, arity: usize) -> Result<Vec<StackEntry>, CalcError> {
+        self.require_operands(token, arity)?;
+        self.save_from(self.stack.len() - arity);
+        Ok(self.stack.split_off(self.stack.len() - arity))
+    }
+
+    // Pushes the result of an operator, its expression is built from the expressions of the operands
+    fn push_result(&mut self, token: &str, value: Value, operands: Vec<StackEntry>) {
+        let expressions = operands.into_iter().map(|entry| entry.expr).collect();
+        self.stack.push(StackEntry { value, expr: Expr::operation(token, expressions) });
+    }
+
+    fn push_leaf(&mut self, value: Value, expr: Expr) {
+        self.stack.push(StackEntry { value, expr });
+    }
+
+    // Replaces the operands by the result of the function of the operator
+    fn function_handling(&mut self, token: &str, arity: usize, function: Function) -> Result<(), CalcError> {
+        let operands = self.pop_operands(token, arity)?;
+        let values: Vec<Value> = operands.iter().map(|entry| entry.value.clone()).collect();
+        let result = function(self, token, &values)?;
+        self.push_result(token, result, operands);
+        Ok(())
+    }
+
+    // Applies a binary operator, exactly if both values are exact and the result is rational.
+    // Vectors and matrices are combined element by element, quantities keep track of their unit
+    pub(crate) fn combine(token: &str, a: &Value, b: &Value) -> Result<Value, CalcError> {
+        if a.is_symbolic() || b.is_symbolic() {
+            return symbolic::combine(token, a, b);
+        }
+        if (a.is_array() && b.is_quantity()) || (a.is_quantity() && b.is_array()) {
+            return Err(CalcError::DomainError("vectors and matrices cannot have units".to_owned()));
+        }
+        if a.is_array() || b.is_array() {
+            return matrix::elementwise(token, a, b);
+        }
+        if a.is_quantity() || b.is_quantity() {
+            return units::combine(token, a, b);
+        }
+        if a.is_complex() || b.is_complex() {
+            return Self::complex_arithmetic(token, &a.to_complex(), &b.to_complex());
+        }
+        if let (Value::Exact(x), Value::Exact(y)) = (a, b) {
+            if let Some(result) = Self::exact_arithmetic(token, x, y)? {
+                return Ok(Value::Exact(result));
+            }
+        }
+
+        let (a, b) = (a.to_f64(), b.to_f64());
+        let result = match token {
+            "+" => a + b,
+            "-" => a - b,
+            "*" => a * b,
+            "/" if b == 0.0 => return Err(CalcError::DomainError("division by zero".to_owned())),
+            "/" => a / b,
+            // Fractional powers of negative numbers are complex
+            "^" if a < 0.0 && b.fract() != 0.0 => {
+                return Self::complex_arithmetic(token, &Complex::from_real(a), &Complex::from_real(b));
+            }
+            "^" => a.powf(b),
+            "mod" if b == 0.0 => return Err(CalcError::DomainError("modulo by zero".to_owned())),
+            // The result has the sign of the divisor, like the exact version
+            "mod" => a - b * (a / b).floor(),
+            _ => return Err(CalcError::DomainError(format!("'{}' is not defined for real numbers", token))),
+        };
+        if result.is_nan() {
+            return Err(CalcError::DomainError(format!("{} {} {} is not a real number", a, token, b)));
+        }
+        Ok(Value::Float(result))
+    }
+
+    // Arithmetic as soon as one operand is complex, results without imaginary part are real again
+    fn complex_arithmetic(token: &str, x: &Complex, y: &Complex) -> Result<Value, CalcError> {
+        let result = match token {
+            "+" => x.add(y),
+            "-" => x.sub(y),
+            "*" => x.mul(y),
+            "/" => x.div(y).ok_or(CalcError::DomainError("division by zero".to_owned()))?,
+            "^" => x.pow(y).ok_or(CalcError::DomainError("zero to a non-positive power".to_owned()))?,
+            _ => {
+                return Err(CalcError::DomainError(format!("'{}' is not defined for complex numbers", token)));
+            }
+        };
+        Ok(Value::from_complex(result))
+    }
+
+    // None if the result is not rational or too large, so the caller falls back to floating point
+    fn exact_arithmetic(token: &str, x: &Rational, y: &Rational) -> Result<Option<Rational>, CalcError> {
+        let result = match token {
+            "+" => x.add(y),
+            "-" => x.sub(y),
+            "*" => x.mul(y),
+            "/" => x.div(y).ok_or(CalcError::DomainError("division by zero".to_owned()))?,
+            "mod" => {
+                let quotient = x.div(y).ok_or(CalcError::DomainError("modulo by zero".to_owned()))?;
+                x.sub(&y.mul(&Rational::from_integer(quotient.floor())))
+            }
+            "^" => {
+                let exponent = match y.is_integer().then(|| y.numerator().to_string().parse::<i64>()) {
+                    Some(Ok(exponent)) => exponent,
+                    _ => return Ok(None),
+                };
+                let base_bits = x.numerator().bits().max(x.denominator().bits());
+                if base_bits.saturating_mul(exponent.unsigned_abs()) > MAX_EXACT_POWER_BITS {
+                    return Ok(None);
+                }
+                x.pow(exponent).ok_or(CalcError::DomainError("zero to a negative power".to_owned()))?
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+
+    // The smaller value for Ordering::Less and the larger one for Ordering::Greater, it is kept as it is,
+    // exact or not
+    pub(crate) fn extremum(token: &str, values: &[Value], ordering: Ordering) -> Result<Value, CalcError> {
+        let (x, y) = (real_operand(token, &values[0])?, real_operand(token, &values[1])?);
+        Ok(if y.partial_cmp(&x) == Some(ordering) { values[1].clone() } else { values[0].clone() })
+    }
+
+    pub(crate) fn complex_from_parts(&self, token: &str, values: &[Value]) -> Result<Value, CalcError> {
+        let (x, y) = (real_operand(token, &values[0])?, real_operand(token, &values[1])?);
+        Ok(Value::from_complex(Complex::new(x, y)))
+    }
+
+    // Negative and complex numbers have complex roots, perfect squares stay exact
+    pub(crate) fn square_root(value: &Value) -> Value {
+        let a = value.to_f64();
+        match value {
+            _ if value.is_complex() || a < 0.0 => Value::from_complex(value.to_complex().sqrt()),
+            Value::Exact(x) => x.exact_sqrt().map_or(Value::Float(a.sqrt()), Value::Exact),
+            _ => Value::Float(a.sqrt()),
+        }
+    }
+
+    pub(crate) fn absolute_value(value: &Value) -> Value {
+        match value {
+            Value::Exact(x) => Value::Exact(x.abs()),
+            Value::Complex(z) => Value::Float(z.abs()),
+            _ => Value::Float(value.to_f64().abs()),
+        }
+    }
+
+    // Logarithm with the given real function, 'scale' is the natural logarithm of its base.
+    // Negative and complex numbers have complex logarithms
+    pub(crate) fn logarithm(value: &Value, function: fn(f64) -> f64, scale: f64) -> Result<Value, CalcError> {
+        let a = value.to_f64();
+        if a == 0.0 {
+            return Err(CalcError::DomainError("logarithm of zero".to_owned()));
+        }
+        if !value.is_complex() && a > 0.0 {
+            return Ok(Value::Float(function(a)));
+        }
+        let ln = value.to_complex().ln().unwrap();
+        Ok(Value::from_complex(Complex::new(ln.re / scale, ln.im / scale)))
+    }
+
+    // sin, cos and tan of an angle in the selected unit
+    pub(crate) fn angle_function(
+        &self,
+        token: &str,
+        value: &Value,
+        function: fn(f64) -> f64,
+    ) -> Result<Value, CalcError> {
+        let a = real_operand(token, value)?;
+        let angle = if self.angle_mode == AngleMode::Degrees { a.to_radians() } else { a };
+        Ok(Value::Float(function(angle)))
+    }
+
+    // Inverse functions return an angle in the selected unit
+    pub(crate) fn inverse_angle_function(
+        &self,
+        token: &str,
+        value: &Value,
+        function: fn(f64) -> f64,
+    ) -> Result<Value, CalcError> {
+        let a = real_operand(token, value)?;
+        let angle = function(a);
+        if angle.is_nan() {
+            return Err(CalcError::DomainError(format!("{} of {} is not defined", token, a)));
+        }
+        Ok(self.angle_value(angle))
+    }
+
+    // 'y x atan2' is the angle of the point (x, y)
+    pub(crate) fn two_argument_arctangent(&self, token: &str, values: &[Value]) -> Result<Value, CalcError> {
+        let x = real_operand(token, &values[1])?;
+        let y = real_operand(token, &values[0])?;
+        Ok(self.angle_value(y.atan2(x)))
+    }
+
+    fn angle_value(&self, radians: f64) -> Value {
+        Value::Float(if self.angle_mode == AngleMode::Degrees { radians.to_degrees() } else { radians })
+    }
+
+    pub(crate) fn exponential(value: &Value) -> Value {
+        match value {
+            Value::Complex(z) => Value::from_complex(z.exp()),
+            _ => Value::Float(value.to_f64().exp()),
+        }
+    }
+
+    // Parts of complex numbers, real numbers are complex numbers without imaginary part
+    pub(crate) fn real_part(value: &Value) -> Value {
+        match value {
+            Value::Complex(z) => Value::Float(z.re),
+            _ => value.clone(),
+        }
+    }
+
+    pub(crate) fn imaginary_part(value: &Value) -> Value {
+        match value {
+            Value::Exact(_) => Value::Exact(Rational::from_integer(BigInt::zero())),
+            _ => Value::Float(value.to_complex().im),
+        }
+    }
+
+    pub(crate) fn conjugate(value: &Value) -> Value {
+        match value {
+            Value::Complex(z) => Value::Complex(z.conj()),
+            _ => value.clone(),
+        }
+    }
+
+    // The angle is given in the selected unit like the inverse trigonometric functions
+    pub(crate) fn argument(&self, value: &Value) -> Value {
+        self.angle_value(value.to_complex().arg())
+    }
+
+    // Integers in two's complement like in most programming languages, so '0 not' is -1.
+    // Unary operations get 0 as their second integer
+    pub(crate) fn bitwise(
+        token: &str,
+        values: &[Value],
+        operation: fn(i128, i128) -> i128,
+    ) -> Result<Value, CalcError> {
+        let integers = values
+            .iter()
+            .map(|value| integer_operand(token, value))
+            .collect::<Result<Vec<i128>, CalcError>>()?;
+        let result = operation(integers[0], integers.get(1).copied().unwrap_or_default());
+        Ok(integer_result(values, result))
+    }
+
+    // 'shl' and 'shr' by 0 to 127 bits
+    pub(crate) fn shift(token: &str, values: &[Value], left: bool) -> Result<Value, CalcError> {
+        let (a, b) = (integer_operand(token, &values[0])?, integer_operand(token, &values[1])?);
+        if !(0..128).contains(&b) {
+            return Err(CalcError::DomainError(format!("'{}' needs a shift from 0 to 127, got {}", token, b)));
+        }
+        // Bits shifted out at the top would change the value
+        if left && (a << b) >> b != a {
+            return Err(CalcError::DomainError(format!("{} shl {} does not fit into 128 bits", a, b)));
+        }
+        Ok(integer_result(values, if left { a << b } else { a >> b }))
+    }
+
+    // Exact values are rounded to an exact integer with the first function, floats with the second
+    pub(crate) fn rounding(
+        token: &str,
+        value: &Value,
+        exact: fn(&Rational) -> BigInt,
+        float: fn(f64) -> f64,
+    ) -> Result<Value, CalcError> {
+        match value {
+            Value::Exact(x) => Ok(Value::Exact(Rational::from_integer(exact(x)))),
+            Value::Float(a) => Ok(Value::Float(float(*a))),
+            value => Err(not_real(token, value)),
+        }
+    }
+
+    pub(crate) fn constant_handling(&mut self, token: &str, value: f64) -> Result<(), CalcError> {
+        self.push_leaf(Value::Float(value), Expr::Constant(token.to_owned()));
+        Ok(())
+    }
+
+    pub(crate) fn factorial(&self, token: &str, values: &[Value]) -> Result<Value, CalcError> {
+        let value = &values[0];
+        let a = real_operand(token, value)?;
+        if a.fract() != 0.0 || a < 0.0 {
+            return Err(CalcError::InvalidFactorial(a));
+        }
+
+        match value {
+            Value::Exact(_) if a > MAX_EXACT_FACTORIAL as f64 => Err(CalcError::FactorialOverflow(a)),
+            Value::Exact(_) => {
+                let product = (2..=a as u64)
+                    .fold(BigInt::one(), |acc, factor| &acc * &BigInt::from(factor));
+                Ok(Value::Exact(Rational::from_integer(product)))
+            }
+            // 20! is the largest factorial that still fits into u64
+            _ if a > 20.0 => Err(CalcError::FactorialOverflow(a)),
+            _ => Ok(Value::Float((1..=a as u64).product::<u64>() as f64)),
+        }
+    }
+
+    pub(crate) fn full_stack_addition_handling(&mut self) -> Result<(), CalcError> {
+        self.require_operands("++", 1)?;
+        if self.stack.len() == 1 && self.stack[0].value.is_array() {
+            return self.reduce_array("+", "sum");
+        }
+        let result = self.fold_stack("+")?;
+
+        self.save_from(0);
+        self.stack.clear();
+        self.stack.push(result);
+        Ok(())
+    }
+
+    pub(crate) fn full_stack_multiplication_handling(&mut self) -> Result<(), CalcError> {
+        self.require_operands("**", 1)?;
+        if self.stack.len() == 1 && self.stack[0].value.is_array() {
+            return self.reduce_array("*", "prod");
+        }
+        let result = self.fold_stack("*")?;
+
+        self.save_from(0);
+        self.stack.clear();
+        self.stack.push(result);
+        Ok(())
+    }
+
+    // 'linreg' replaces the whole stack like the other statistics, but takes it as pairs x1 y1 x2 y2 ...
+    // and leaves the intercept and the slope
+    pub(crate) fn linear_regression_handling(&mut self, token: &str) -> Result<(), CalcError> {
+        statistics::check_pairs(self.stack.len())?;
+        // Two pairs at least
+        let operands = self.pop_operands(token, self.stack.len().max(4))?;
+        let values: Vec<Value> = operands.iter().map(|entry| entry.value.clone()).collect();
+        let (intercept, slope) = statistics::linear_regression(&values)?;
+        self.push_result("intercept", intercept, operands.clone());
+        self.push_result("slope", slope, operands);
+        Ok(())
+    }
+
+    // A single vector on the stack is summed or multiplied along its elements, a matrix along its columns
+    fn reduce_array(&mut self, token: &str, name: &str) -> Result<(), CalcError> {
+        let operands = self.pop_operands(token, 1)?;
+        let result = matrix::reduce(token, &operands[0].value)?;
+        self.push_result(name, result, operands);
+        Ok(())
+    }
+
+    // Combines the whole stack from the bottom to the top with the given operator
+    fn fold_stack(&self, token: &str) -> Result<StackEntry, CalcError> {
+        let mut entries = self.stack.iter();
+        let first = entries.next().unwrap().clone();
+        entries.try_fold(first, |acc, entry| {
+            Ok(StackEntry {
+                value: Self::combine(token, &acc.value, &entry.value)?,
+                expr: Expr::operation(token, vec![acc.expr, entry.expr.clone()]),
+            })
+        })
+    }
+
+    // Words that move the entries from the given depth on, 'pick' and 'roll' take their depth from the stack
+    pub(crate) fn stack_word_handling(
+        &mut self,
+        token: &str,
+        depth: Option<usize>,
+        rearrangement: Rearrangement,
+    ) -> Result<(), CalcError> {
+        let depth = match depth {
+            Some(depth) => depth,
+            None => {
+                self.require_operands(token, 1)?;
+                let depth = real_operand(token, &self.pop_entry().value)?;
+                if depth.fract() != 0.0 || depth < 1.0 {
+                    return Err(CalcError::DomainError(format!(
+                        "'{}' needs a positive integer depth, got {}",
+                        token, depth
+                    )));
+                }
+                depth as usize
+            }
+        };
+        self.require_operands(token, depth)?;
+        // Picking only pushes, the other rearrangements change the entries from the depth on
+        let changed = match rearrangement {
+            Rearrangement::Clear => 0,
+            Rearrangement::Pick => self.stack.len(),
+            Rearrangement::Roll | Rearrangement::Drop => self.stack.len() - depth,
+        };
+        self.save_from(changed);
+        rearrange(&mut self.stack, rearrangement, depth);
+        Ok(())
+    }
+
+    // 'x' pushes the symbolic variable
+    fn symbol_handling(&mut self) -> Result<(), CalcError> {
+        self.push_leaf(Value::Symbolic(symbolic::symbol()), symbolic::symbol());
+        Ok(())
+    }
+
+    // 'diff' derives the top expression with respect to x, results without x are evaluated right away
+    pub(crate) fn differentiate(&self, token: &str, values: &[Value]) -> Result<Value, CalcError> {
+        let degrees = self.angle_mode == AngleMode::Degrees;
+        let Some(derivative) = symbolic::derivative(&symbolic::to_expr(&values[0]), degrees) else {
+            return Err(CalcError::DomainError(format!("'{}' cannot derive {}", token, values[0])));
+        };
+        self.symbolic_value(symbolic::simplify(&derivative))
+    }
+
+    // 'simp' simplifies the top expression, numbers stay as they are
+    pub(crate) fn simplification(&self, _: &str, values: &[Value]) -> Result<Value, CalcError> {
+        match &values[0] {
+            value @ Value::Symbolic(_) => self.symbolic_value(symbolic::simplify(&symbolic::to_expr(value))),
+            value => Ok(value.clone()),
+        }
+    }
+
+    // Operators from the config file run their tokens on the operands, on a calculator with the same modes.
+    // The operands are not checked, the tokens decide which values they take
+    fn custom_operator_handling(&mut self, token: &str, arity: usize, body: &[String]) -> Result<(), CalcError> {
+        self.require_operands(token, arity)?;
+        self.save_from(self.stack.len() - arity);
+        let operands = self.stack.split_off(self.stack.len() - arity);
+        let mut scratch = self.scratch();
+        scratch.stack = operands.clone();
+        scratch.evaluate_line(&body.join(" "))?;
+        if scratch.stack.len() != 1 {
+            return Err(CalcError::DomainError(format!(
+                "'{}' has to leave exactly one value, but left {}",
+                token,
+                scratch.stack.len()
+            )));
+        }
+        self.push_result(token, scratch.stack.pop().unwrap().value, operands);
+        Ok(())
+    }
+
+    // Functions of an expression of x stay unevaluated like the operators
+    fn symbolic_function_handling(&mut self, token: &str) -> Result<(), CalcError> {
+        let entry = self.pop_entry();
+        let expr = Expr::operation(token, vec![symbolic::to_expr(&entry.value)]);
+        self.push_result(token, Value::Symbolic(expr), vec![entry]);
+        Ok(())
+    }
+
+    // '@value' is the long form 'at value', it evaluates the top expression of x at that value
+    fn evaluation_handling(&mut self, point: &str) -> Result<(), CalcError> {
+        if self.parse_number(point).is_none_or(|value| value.is_array()) {
+            return Err(CalcError::DomainError(format!("'at' needs a number, got '{}'", point)));
+        }
+        let operands = self.pop_operands("at", 1)?;
+        let value = match &operands[0].value {
+            Value::Symbolic(expr) => self.evaluate_symbolic(expr, point)?,
+            value if value.is_array() || value.is_quantity() => return Err(not_real("at", value)),
+            value => value.clone(),
+        };
+        self.push_result(&format!("@{}", point), value, operands);
+        Ok(())
+    }
+
+    fn symbolic_value(&self, expr: Expr) -> Result<Value, CalcError> {
+        if symbolic::contains_symbol(&expr) {
+            return Ok(Value::Symbolic(expr));
+        }
+        self.evaluate_symbolic(&expr, "x")
+    }
+
+    // Empty calculator with the same modes and operators, for running a part of a calculation
+    fn scratch(&self) -> RPNCalculator {
+        let operators = Rc::clone(&self.operators);
+        RPNCalculator { mode: self.mode, angle_mode: self.angle_mode, ..RPNCalculator::with_operators(operators) }
+    }
+
+    // Runs the tokens of an expression on a calculator with the same modes, x is replaced by the given number
+    fn evaluate_symbolic(&self, expr: &Expr, point: &str) -> Result<Value, CalcError> {
+        let mut scratch = self.scratch();
+        for token in expr.to_rpn() {
+            scratch.apply_operation(if token == "x" { point } else { &token })?;
+        }
+        Ok(scratch.stack.pop().unwrap().value)
+    }
+
+    // A unit word gives the top value that unit, so '3 m' is three meters and '3 m s' is three meter seconds
+    fn unit_handling(&mut self, token: &str) -> Result<(), CalcError> {
+        let unit = Unit::parse(token).unwrap();
+        self.require_operands(token, 1)?;
+        let entry = self.pop_entry();
+        if entry.value.is_array() || entry.value.is_symbolic() {
+            return Err(not_real(token, &entry.value));
+        }
+        let value = units::attach(&entry.value, &unit)?;
+        self.push_result(&unit.to_string(), value, vec![entry]);
+        Ok(())
+    }
+
+    // '->unit' is the long form 'to unit', it converts the top quantity into a unit of the same dimension
+    fn conversion_handling(&mut self, text: &str) -> Result<(), CalcError> {
+        let unit = Unit::parse_target(text).ok_or(CalcError::UnknownToken(text.to_owned()))?;
+        self.require_operands("to", 1)?;
+        let entry = self.pop_entry();
+        let value = units::convert(&entry.value, &unit)?;
+        self.push_result(&format!("->{}", unit), value, vec![entry]);
+        Ok(())
+    }
+
+    // '=name' pops the top of the stack into the variable, use 'dup =name' to keep it on the stack
+    fn store_variable_handling(&mut self, name: &str) -> Result<(), CalcError> {
+        if !is_valid_name(name) {
+            return Err(CalcError::InvalidName(name.to_owned()));
+        }
+        self.require_operands("sto", 1)?;
+        let entry = self.pop_entry();
+        self.save_variables();
+        self.variables.insert(name.to_owned(), entry.value);
+        Ok(())
+    }
+
+    // '$name' pushes the value of the variable, its expression keeps the name
+    fn recall_variable_handling(&mut self, name: &str) -> Result<(), CalcError> {
+        match self.variables.get(name).cloned() {
+            Some(value) => {
+                self.push_leaf(value, Expr::Variable(name.to_owned()));
+                Ok(())
+            }
+            None => Err(CalcError::UnknownVariable(name.to_owned())),
+        }
+    }
+
+    fn new_number_handling(&mut self, token: &str) -> Result<(), CalcError> {
+        let value = self.parse_number(token).ok_or(CalcError::UnknownToken(token.to_owned()))?;
+        self.push_leaf(value, Expr::Number(token.to_owned()));
+        Ok(())
+    }
+
+    // Numbers are exact in exact mode, tokens like 'inf' that have no exact value stay floats.
+    // The elements of vector and matrix literals follow the same rules
+    fn parse_number(&self, token: &str) -> Option<Value> {
+        if token.starts_with('[') {
+            return matrix::parse_array(token, |element| self.parse_number(element)).map(matrix::to_value);
+        }
+        if let Some(integer) = format::parse_integer_literal(token) {
+            return Some(match self.mode {
+                NumberMode::Exact => Value::Exact(Rational::from_integer(integer)),
+                NumberMode::Float => Value::Float(integer.to_f64()),
+            });
+        }
+        if self.mode == NumberMode::Exact {
+            if let Some(num) = Rational::parse(token) {
+                return Some(Value::Exact(num));
+            }
+        }
+        match token.parse::<f64>() {
+            Ok(num) => Some(Value::Float(num)),
+            Err(_) => Complex::parse(token).map(Value::from_complex),
+        }
+    }
+
+    // Applying a whole line of whitespace separated tokens, the line is rolled back as a whole if one token fails.
+    // Only the changes of the line are reverted, so long scripts do not copy the whole undo history per line
+    pub fn evaluate_line(&mut self, line: &str) -> Result<(), CalcError> {
+        let words = group_literals(line)?;
+        let settings = (self.mode, self.angle_mode, self.radix, self.number_format);
+        let definitions = words.iter().any(|word| word == ":").then(|| self.definitions.clone());
+        self.line_changes = Some(Vec::new());
+        let result = self.evaluate_tokens(&words);
+        let changes = self.line_changes.take().unwrap();
+        if result.is_err() {
+            for change in changes.into_iter().rev() {
+                self.revert(change);
+            }
+            (self.mode, self.angle_mode, self.radix, self.number_format) = settings;
+            if let Some(definitions) = definitions {
+                self.definitions = definitions;
+            }
+        }
+        result
+    }
+
+    // Converts an infix expression to RPN and evaluates it like a line, returns the RPN tokens
+    pub fn evaluate_infix(&mut self, expression: &str) -> Result<Vec<String>, CalcError> {
+        let tokens = parser::infix_to_rpn_with(expression, &self.operators)?;
+        self.evaluate_line(&tokens.join(" "))?;
+        Ok(tokens)
+    }
+
+    fn evaluate_tokens(&mut self, words: &[String]) -> Result<(), CalcError> {
+        let mut tokens = words.iter().map(String::as_str);
+        while let Some(token) = tokens.next() {
+            match token {
+                // ': name body ;' defines a new word
+                ":" => {
+                    let name = tokens.next().ok_or(CalcError::MissingArgument(token.to_owned()))?;
+                    let mut body = Vec::new();
+                    let mut terminated = false;
+                    for word in tokens.by_ref() {
+                        if word == ";" {
+                            terminated = true;
+                            break;
+                        }
+                        body.push(word);
+                    }
+                    if !terminated {
+                        return Err(CalcError::UnterminatedDefinition(name.to_owned()));
+                    }
+                    self.define(name, &body)?;
+                }
+                // 'sto name', 'rcl name', 'to unit' and 'at value' are the long forms of '=name', '$name',
+                // '->unit' and '@value'
+                "sto" | "rcl" | "to" | "at" => {
+                    let name = tokens.next().ok_or(CalcError::MissingArgument(token.to_owned()))?;
+                    self.apply_operation(&format!("{}{}", long_form_prefix(token), name))?;
+                }
+                // 'fix 4', 'sci 4' and 'eng 4' select the digits that are printed
+                "fix" | "sci" | "eng" => {
+                    let argument = tokens.next().ok_or(CalcError::MissingArgument(token.to_owned()))?;
+                    let digits = argument.parse::<usize>().map_err(|_| {
+                        CalcError::DomainError(format!("'{}' needs a number of digits, got '{}'", token, argument))
+                    })?;
+                    self.set_number_format(NumberFormat::from_command(token, digits).unwrap())?;
+                }
+                _ => self.apply_operation(token)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Defines or redefines a word. Other user words in the body are expanded right away, so
+    // redefining them later does not change this word and a word can never call itself
+    pub fn define(&mut self, name: &str, body: &[&str]) -> Result<(), CalcError> {
+        if !is_valid_name(name) || BUILTIN_WORDS.contains(&name) || self.operators.is_word(name) {
+            return Err(CalcError::InvalidName(name.to_owned()));
+        }
+
+        let mut compiled = Vec::new();
+        let mut words = body.iter();
+        while let Some(&word) = words.next() {
+            match word {
+                "sto" | "rcl" | "to" | "at" => {
+                    let argument = words.next().ok_or(CalcError::MissingArgument(word.to_owned()))?;
+                    compiled.push(format!("{}{}", long_form_prefix(word), argument));
+                }
+                ":" | ";" | "undo" | "redo" | "exact" | "float" | "deg" | "rad" | "dec" | "hex" | "oct"
+                | "bin" | "std" | "fix" | "sci" | "eng" => {
+                    return Err(CalcError::InvalidDefinition(format!(
+                        "'{}' is not allowed inside a definition",
+                        word
+                    )));
+                }
+                _ => match self.definitions.get(word) {
+                    Some(inner) => compiled.extend(inner.iter().cloned()),
+                    None if is_known_word(word, &self.operators) => compiled.push(word.to_owned()),
+                    None => return Err(CalcError::UnknownToken(word.to_owned())),
+                },
+            }
+        }
+        self.definitions.insert(name.to_owned(), compiled);
+        Ok(())
+    }
+
+    // All user defined words with their expanded body, sorted by name
+    pub fn definitions(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.definitions.iter().map(|(name, body)| (name.as_str(), body.as_slice()))
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.definitions.contains_key(name)
+    }
+
+    // Every word that can be typed right now: builtins, user words and the variable accesses
+    pub fn completions(&self) -> Vec<String> {
+        let mut words: Vec<String> = BUILTIN_WORDS.iter().map(|word| word.to_string()).collect();
+        words.extend(self.operators.names().map(str::to_owned));
+        words.extend(units::symbols().map(str::to_owned));
+        words.extend(self.definitions.keys().cloned());
+        for name in self.variables.keys() {
+            words.push(format!("${}", name));
+            words.push(format!("={}", name));
+        }
+        words
+    }
+
+    // Human readable dump of the whole state, one item per line. Undo and redo start over after loading
+    pub fn save_session(&self) -> String {
+        let mut lines = vec!["# RPN calculator session".to_owned()];
+        let mode = if self.mode == NumberMode::Exact { "exact" } else { "float" };
+        let angle = if self.angle_mode == AngleMode::Degrees { "deg" } else { "rad" };
+        lines.push(format!("mode {}", mode));
+        lines.push(format!("angle {}", angle));
+        lines.push(format!("radix {}", self.radix.name()));
+        lines.push(format!("format {}", self.number_format.command()));
+        for (name, body) in &self.definitions {
+            lines.push(format!("word {} {}", name, body.join(" ")));
+        }
+        for (name, value) in &self.variables {
+            lines.push(format!("var {} {}", name, value_to_session(value)));
+        }
+        for entry in &self.stack {
+            lines.push(format!("entry {} = {}", value_to_session(&entry.value), entry.expr.to_rpn().join(" ")));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    // Replaces the whole state with a dump of save_session, nothing changes if the dump is invalid
+    pub fn load_session(&mut self, text: &str) -> Result<(), CalcError> {
+        let mut loaded = RPNCalculator::with_operators(Rc::clone(&self.operators));
+        for (index, line) in text.lines().enumerate() {
+            let invalid = |message: String| CalcError::InvalidSession { line: index + 1, message };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                [first, ..] if first.starts_with('#') => {}
+                ["mode", "float"] => loaded.mode = NumberMode::Float,
+                ["mode", "exact"] => loaded.mode = NumberMode::Exact,
+                ["angle", "rad"] => loaded.angle_mode = AngleMode::Radians,
+                ["angle", "deg"] => loaded.angle_mode = AngleMode::Degrees,
+                ["radix", name] if Radix::from_name(name).is_some() => {
+                    loaded.radix = Radix::from_name(name).unwrap();
+                }
+                ["format", "std"] => loaded.number_format = NumberFormat::Standard,
+                ["format", command @ ("fix" | "sci" | "eng"), digits] => {
+                    let digits = digits.parse::<usize>().map_err(|_| invalid(format!("invalid digits '{}'", digits)))?;
+                    let number_format = NumberFormat::from_command(command, digits).unwrap();
+                    loaded.set_number_format(number_format).map_err(|error| invalid(error.to_string()))?;
+                }
+                ["word", name, body @ ..] => {
+                    loaded.define(name, body).map_err(|error| invalid(error.to_string()))?;
+                }
+                ["var", name, kind, value] if is_valid_name(name) => {
+                    let value = value_from_session(kind, value)
+                        .ok_or_else(|| invalid(format!("invalid value '{} {}'", kind, value)))?;
+                    loaded.variables.insert(name.to_string(), value);
+                }
+                ["entry", kind, value, "=", expr @ ..] => {
+                    let value = value_from_session(kind, value)
+                        .ok_or_else(|| invalid(format!("invalid value '{} {}'", kind, value)))?;
+                    let expr = Expr::from_rpn_with(expr, &self.operators)
+                        .ok_or_else(|| invalid(format!("invalid expression '{}'", expr.join(" "))))?;
+                    loaded.stack.push(StackEntry { value, expr });
+                }
+                // Sessions of older versions list every token, the undo history starts over anyway
+                ["history", ..] => {}
+                _ => return Err(invalid(format!("cannot read '{}'", line.trim()))),
+            }
+        }
+        *self = loaded;
+        Ok(())
+    }
+
+    // Values of the current stack, top of the stack is the last element
+    pub fn stack(&self) -> Vec<Value> {
+        self.stack.iter().map(|entry| entry.value.clone()).collect()
+    }
+
+    // Values together with the expressions that produced them, top of the stack is the last element
+    pub fn entries(&self) -> &[StackEntry] {
+        &self.stack
+    }
+
+    // All stored variables, sorted by name
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.variables.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn get_result(&self) -> Option<Value> {
+        if self.stack.len() == 1 { self.stack.last().map(|entry| entry.value.clone()) } else { None }
+    }
+
+    // Infix form of the top stack entry
+    pub fn infix(&self) -> String {
+        self.stack.last().map(|entry| entry.expr.to_infix_with(&self.operators)).unwrap_or_default()
+    }
+
+    // LaTeX form of the top stack entry
+    pub fn latex(&self) -> String {
+        self.stack.last().map(|entry| entry.expr.to_latex_with(&self.operators)).unwrap_or_default()
+    }
+
+    // Samples the function of x on top of the stack at evenly spaced points from xmin to xmax.
+    // Points without a real value are left out of the curve, a number gives a horizontal line
+    pub fn plot(&self, xmin: f64, xmax: f64, samples: usize) -> Result<Plot, CalcError> {
+        self.require_operands("plot", 1)?;
+        let bound = |x: f64| self.format_value(&Value::Float(x));
+        let bounds = format!("{} and {}", bound(xmin), bound(xmax));
+        if xmin >= xmax {
+            return Err(CalcError::DomainError(format!("'plot' needs xmin < xmax, got {}", bounds)));
+        }
+        // The width has to be finite as well, -1e308 to 1e308 would sample nothing but NaN
+        if !(xmax - xmin).is_finite() {
+            return Err(CalcError::DomainError(format!("'plot' needs a finite range, got {}", bounds)));
+        }
+        let value = &self.stack.last().unwrap().value;
+        if !matches!(value, Value::Symbolic(_) | Value::Exact(_) | Value::Float(_)) {
+            return Err(not_real("plot", value));
+        }
+        let expr = symbolic::to_expr(value);
+        // Samples are floats anyway, exact arithmetic would only make them slow
+        let float = RPNCalculator { mode: NumberMode::Float, ..self.scratch() };
+        let samples = samples.max(2);
+        let points: Vec<(f64, f64)> = (0..samples)
+            .map(|index| {
+                let x = xmin + (xmax - xmin) * index as f64 / (samples - 1) as f64;
+                let y = float.evaluate_symbolic(&expr, &x.to_string()).map_or(f64::NAN, |value| value.to_f64());
+                (x, y)
+            })
+            .collect();
+        if points.iter().all(|(_, y)| !y.is_finite()) {
+            return Err(CalcError::DomainError(format!("'plot' found no real values between {}", bounds)));
+        }
+        Ok(Plot::new(&expr.to_infix_with(&self.operators), points))
+    }
+}
+
+// Values are tagged in session files, since an exact 5 and a float 5 look the same
+fn value_to_session(value: &Value) -> String {
+    match value {
+        Value::Exact(value) => format!("exact {}", value),
+        Value::Float(value) => format!("float {}", value),
+        Value::Complex(value) => format!("complex {}", value),
+        // Every element carries its own tag: "array [exact:1,float:0.5]"
+        Value::Vector(_) | Value::Matrix(_) => {
+            let array = matrix::from_value(value).unwrap();
+            let tagged = array.map(|element| value_to_session(element).replacen(' ', ":", 1));
+            format!("array {}", matrix::format_array(&tagged, ","))
+        }
+        // The magnitude is tagged like an array element: "quantity exact:3:km/h"
+        Value::Quantity(magnitude, unit) => {
+            format!("quantity {}:{}", value_to_session(magnitude).replacen(' ', ":", 1), unit)
+        }
+        // The tokens of the expression: "symbolic x,2,^"
+        Value::Symbolic(expr) => format!("symbolic {}", expr.to_rpn().join(",")),
+    }
+}
+
+fn value_from_session(kind: &str, text: &str) -> Option<Value> {
+    match kind {
+        "exact" => Rational::parse(text).map(Value::Exact),
+        "float" => text.parse::<f64>().ok().map(Value::Float),
+        "complex" => Complex::parse(text).map(Value::Complex),
+        "array" => {
+            let element = |element: &str| {
+                element.split_once(':').and_then(|(kind, text)| value_from_session(kind, text))
+            };
+            matrix::parse_array(text, element).map(matrix::to_value)
+        }
+        "quantity" => {
+            let mut parts = text.splitn(3, ':');
+            let (kind, magnitude, unit) = (parts.next()?, parts.next()?, parts.next()?);
+            let magnitude = value_from_session(kind, magnitude)?;
+            Some(Value::Quantity(Box::new(magnitude), Unit::parse_target(unit)?))
+        }
+        "symbolic" => Expr::from_rpn(&text.split(',').collect::<Vec<&str>>()).map(Value::Symbolic),
+        _ => None,
+    }
+}
+
+// Operands of functions that only exist for real numbers
+fn real_operand(token: &str, value: &Value) -> Result<f64, CalcError> {
+    match value {
+        Value::Complex(_) | Value::Vector(_) | Value::Matrix(_) | Value::Quantity(_, _) | Value::Symbolic(_) => {
+            Err(not_real(token, value))
+        }
+        _ => Ok(value.to_f64()),
+    }
+}
+
+fn not_real(token: &str, value: &Value) -> CalcError {
+    let kind = match value {
+        Value::Vector(_) => "vector",
+        Value::Matrix(_) => "matrix",
+        Value::Quantity(_, _) => "quantity",
+        Value::Symbolic(_) => "expression",
+        _ => "complex number",
+    };
+    CalcError::DomainError(format!("'{}' is not defined for the {} {}", token, kind, value))
+}
+
+fn long_form_prefix(word: &str) -> &'static str {
+    match word {
+        "sto" => "=",
+        "rcl" => "$",
+        "at" => "@",
+        _ => "->",
+    }
+}
+
+// Joins the words of a bracketed literal like "[1 2 3]" into a single token "[1,2,3]"
+pub(crate) fn group_literals(line: &str) -> Result<Vec<String>, CalcError> {
+    let mut tokens = Vec::new();
+    let mut words = line.split_whitespace();
+    while let Some(word) = words.next() {
+        if !word.starts_with('[') {
+            tokens.push(word.to_owned());
+            continue;
+        }
+        let mut literal = word.to_owned();
+        let mut depth = matrix::bracket_depth(word);
+        while depth > 0 {
+            let Some(next) = words.next() else { break };
+            depth += matrix::bracket_depth(next);
+            literal.push(' ');
+            literal.push_str(next);
+        }
+        tokens.push(matrix::canonical(&literal).ok_or(CalcError::UnknownToken(literal))?);
+    }
+    Ok(tokens)
+}
+
+// Variable names start with a letter and continue with letters, digits or '_'
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(first) if first.is_alphabetic())
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// Words that may appear in a definition body: builtins, operators, numbers, literals, variable accesses,
+// units and evaluations
+pub(crate) fn is_known_word(word: &str, operators: &Operators) -> bool {
+    BUILTIN_WORDS.contains(&word)
+        || operators.is_word(word)
+        || is_number(word)
+        || (word.starts_with('[') && matrix::parse_array(word, |element| is_number(element).then_some(())).is_some())
+        || ((word.starts_with('=') || word.starts_with('$')) && is_valid_name(&word[1..]))
+        || Unit::parse(word).is_some()
+        || word.strip_prefix("->").and_then(Unit::parse_target).is_some()
+        || word.strip_prefix('@').is_some_and(is_number)
+}
+
+fn is_number(word: &str) -> bool {
+    word.parse::<f64>().is_ok()
+        || Rational::parse(word).is_some()
+        || Complex::parse(word).is_some()
+        || format::parse_integer_literal(word).is_some()
+}
+
+// Operands of the bitwise operators, integers that fit into 128 bits
+fn integer_operand(token: &str, value: &Value) -> Result<i128, CalcError> {
+    let integer = match value {
+        Value::Exact(x) if x.is_integer() => x.numerator().to_string().parse::<i128>().ok(),
+        Value::Float(a) => BigInt::from_f64(*a).and_then(|integer| integer.to_string().parse::<i128>().ok()),
+        Value::Complex(_) => return Err(not_real(token, value)),
+        _ => None,
+    };
+    integer.ok_or_else(|| {
+        CalcError::DomainError(format!("'{}' needs integers that fit into 128 bits, got {}", token, value))
+    })
+}
+
+// What a stack word does with the entry at its depth: 'pick' copies it to the top, 'roll' moves it to
+// the top, 'drop' removes everything above it and 'clear' the whole stack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Rearrangement {
+    Pick,
+    Roll,
+    Drop,
+    Clear,
+}
+
+// Stack words work on any kind of stack, so values and their expressions are moved the same way.
+// Depths count from the top of the stack starting at 1, so '1 pick' is 'dup' and '2 roll' is 'swap'
+fn rearrange<T: Clone>(stack: &mut Vec<T>, rearrangement: Rearrangement, depth: usize) {
+    match rearrangement {
+        Rearrangement::Pick => stack.push(stack[stack.len() - depth].clone()),
+        Rearrangement::Roll => {
+            let value = stack.remove(stack.len() - depth);
+            stack.push(value);
+        }
+        Rearrangement::Drop => stack.truncate(stack.len() - depth),
+        Rearrangement::Clear => stack.clear(),
+    }
+}
+
+// Exact integer operands give an exact result
+fn integer_result(operands: &[Value], result: i128) -> Value {
+    if operands.iter().all(Value::is_exact) {
+        Value::Exact(Rational::from_integer(BigInt::parse(&result.to_string()).unwrap()))
+    } else {
+        Value::Float(result as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plus() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("3").unwrap();
+        calculator.apply_operation("+").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(8.0)));
+    }
+    #[test]
+    fn test_minus() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("3").unwrap();
+        calculator.apply_operation("-").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(2.0)));
+    }
+    #[test]
+    fn test_mult() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("3").unwrap();
+        calculator.apply_operation("*").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(15.0)));
+    }
+    #[test]
+    fn test_divide() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("/").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(1.0)));
+    }
+    #[test]
+    fn test_exponent() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("2").unwrap();
+        calculator.apply_operation("^").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(25.0)));
+    }
+
+    #[test]
+    fn test_log() {
+        let mut calculator: RPNCalculator = RPNCalculator::new();
+        calculator.apply_operation("10.0").unwrap();
+        calculator.apply_operation("log").unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(1.0)));
+    }
+    #[test]
+    fn test_abs() {
+        let mut calculator: RPNCalculator = RPNCalculator::new();
+        calculator.apply_operation("16.0").unwrap();
+        calculator.apply_operation("abs").unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(16.0)));
+    }
+    #[test]
+    fn test_sqr() {
+        let mut calculator: RPNCalculator = RPNCalculator::new();
+        calculator.apply_operation("4.0").unwrap();
+        calculator.apply_operation("sqrt").unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(2.0)));
+    }
+
+    #[test]
+    fn test_factorial() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5.0").unwrap();
+        calculator.function_handling("!", 1, RPNCalculator::factorial).unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(120.0)));
+    }
+
+    #[test]
+    fn test_full_stack_addition_handling() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("1.0").unwrap();
+        calculator.apply_operation("2.0").unwrap();
+        calculator.apply_operation("3.0").unwrap();
+        calculator.full_stack_addition_handling().unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(6.0)));
+    }
+
+    #[test]
+    fn test_full_stack_multiplication_handling() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("2.0").unwrap();
+        calculator.apply_operation("3.0").unwrap();
+        calculator.full_stack_multiplication_handling().unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(6.0)));
+    }
+
+    #[test]
+    fn test_new_number_handling() {
+        let mut calculator = RPNCalculator::new();
+        calculator.new_number_handling("42").unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(42.0)));
+    }
+
+    #[test]
+    fn test_get_result() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("42.0").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(42.0)));
+    }
+
+    #[test]
+    fn test_apply_operation() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5").unwrap();
+        calculator.apply_operation("3").unwrap();
+        calculator.apply_operation("+").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(8.0)));
+    }
+
+    #[test]
+    fn test_function_handling() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("5.0").unwrap();
+        calculator.apply_operation("3.0").unwrap();
+        let addition: Function = |_, token, values| RPNCalculator::combine(token, &values[0], &values[1]);
+        calculator.function_handling("+", 2, addition).unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(8.0)));
+    }
+
+    #[test]
+    fn test_square_root() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("16.0").unwrap();
+        calculator.function_handling("sqrt", 1, |_, _, values| Ok(RPNCalculator::square_root(&values[0]))).unwrap();
+        assert_eq!(calculator.stack.pop().map(|entry| entry.value), Some(Value::from(4.0)));
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(
+            calculator.apply_operation("+"),
+            Err(CalcError::StackUnderflow { token: "+".to_owned(), required: 2, available: 0 })
+        );
+        calculator.apply_operation("5").unwrap();
+        assert_eq!(
+            calculator.apply_operation("*"),
+            Err(CalcError::StackUnderflow { token: "*".to_owned(), required: 2, available: 1 })
+        );
+        assert_eq!(calculator.stack(), vec![5.0]);
+        calculator.undo().unwrap();
+        assert_eq!(calculator.undo(), Err(CalcError::NothingToUndo));
+    }
+
+    #[test]
+    fn test_unknown_token() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(calculator.apply_operation("foo"), Err(CalcError::UnknownToken("foo".to_owned())));
+        assert_eq!(calculator.undo(), Err(CalcError::NothingToUndo));
+    }
+
+    #[test]
+    fn test_domain_errors_roll_back() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("-4").unwrap();
+        assert!(matches!(calculator.apply_operation("asin"), Err(CalcError::DomainError(_))));
+        calculator.apply_operation("0").unwrap();
+        assert!(matches!(calculator.apply_operation("log"), Err(CalcError::DomainError(_))));
+        assert!(matches!(calculator.apply_operation("/"), Err(CalcError::DomainError(_))));
+        assert_eq!(calculator.stack(), vec![-4.0, 0.0]);
+        calculator.evaluate_line("undo undo").unwrap();
+        assert_eq!(calculator.undo(), Err(CalcError::NothingToUndo));
+    }
+
+    #[test]
+    fn test_factorial_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.apply_operation("2.5").unwrap();
+        assert_eq!(calculator.apply_operation("!"), Err(CalcError::InvalidFactorial(2.5)));
+        calculator.apply_operation("21").unwrap();
+        assert_eq!(calculator.apply_operation("!"), Err(CalcError::FactorialOverflow(21.0)));
+        assert_eq!(calculator.stack(), vec![2.5, 21.0]);
+
+        // Large numbers are shown with an exponent
+        let error = calculator.evaluate_line("1e300 !").unwrap_err();
+        assert_eq!(error.to_string(), "Factorial of '1e300' is too large to be calculated");
+        let error = calculator.evaluate_line("-1.5e20 !").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid input '-1.5e20', cannot calculate the factorial");
+        assert_eq!(CalcError::FactorialOverflow(21.0).to_string(), "Factorial of '21' is too large to be calculated");
+    }
+
+    #[test]
+    fn test_evaluate_line() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("5 3 + 2 *").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(16.0)));
+        assert_eq!(calculator.stack(), &[16.0]);
+        assert_eq!(calculator.infix(), "(5 + 3) * 2");
+        assert_eq!(calculator.latex(), r"\left(5 + 3\right) \cdot 2");
+    }
+
+    #[test]
+    fn test_evaluate_line_rolls_back() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1").unwrap();
+        assert!(calculator.evaluate_line("2 + +").is_err());
+        assert_eq!(calculator.stack(), &[1.0]);
+        assert_eq!(calculator.infix(), "1");
+    }
+
+    #[test]
+    fn test_evaluate_line_rolls_back_undo_and_settings() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2 3").unwrap();
+        calculator.evaluate_line("undo").unwrap();
+        // The failing line undid, redid, replaced the redo entry and changed the modes
+        assert!(calculator.evaluate_line("undo redo undo 5 exact deg hex : f 2 ; foo").is_err());
+        assert_eq!(calculator.stack(), &[1.0, 2.0]);
+        assert_eq!((calculator.mode(), calculator.angle_mode()), (NumberMode::Float, AngleMode::Radians));
+        assert_eq!(calculator.radix(), Radix::Decimal);
+        assert!(!calculator.is_defined("f"));
+        calculator.redo().unwrap();
+        assert_eq!(calculator.stack(), &[1.0, 2.0, 3.0]);
+        calculator.evaluate_line("undo undo undo").unwrap();
+        assert_eq!(calculator.undo(), Err(CalcError::NothingToUndo));
+    }
+
+    #[test]
+    fn test_long_script() {
+        // Each line used to copy the whole undo history, a script of a few thousand lines took minutes
+        let started = std::time::Instant::now();
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("0").unwrap();
+        for line in 0..5000 {
+            calculator.evaluate_line("1 2 + swap drop dup 2 * drop").unwrap();
+            if line % 10 == 0 {
+                assert!(calculator.evaluate_line("1 2 foo").is_err());
+            }
+        }
+        assert_eq!(calculator.stack(), &[3.0]);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10), "took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_undo_only_changed_entries() {
+        let mut calculator = RPNCalculator::new();
+        let mut states = vec![(calculator.stack(), calculator.infix(), 0)];
+        for token in "1 2 3 4 =a 5 rot 3 roll $a dup 2 pick * =b swap drop 2 pick ++ clear 7".split(' ') {
+            calculator.apply_operation(token).unwrap();
+            states.push((calculator.stack(), calculator.infix(), calculator.variables().count()));
+        }
+        // Every undo and redo goes back to exactly the state before or after the token
+        for state in states.iter().rev().skip(1) {
+            calculator.undo().unwrap();
+            assert_eq!(&(calculator.stack(), calculator.infix(), calculator.variables().count()), state);
+        }
+        for state in states.iter().skip(1) {
+            calculator.redo().unwrap();
+            assert_eq!(&(calculator.stack(), calculator.infix(), calculator.variables().count()), state);
+        }
+    }
+
+    #[test]
+    fn test_long_line() {
+        // Snapshots used to copy the whole stack and every expression tree for each token
+        let started = std::time::Instant::now();
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line(&format!("1{}", " abs".repeat(6000))).unwrap();
+        calculator.evaluate_line(&"1 ".repeat(6000)).unwrap();
+        calculator.evaluate_line("++").unwrap();
+        assert_eq!(calculator.stack(), &[6001.0]);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10), "took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2 3 ++").unwrap();
+        assert_eq!(calculator.stack(), &[6.0]);
+
+        calculator.apply_operation("undo").unwrap();
+        assert_eq!(calculator.stack(), &[1.0, 2.0, 3.0]);
+        assert_eq!(calculator.infix(), "3");
+        calculator.undo().unwrap();
+        assert_eq!(calculator.stack(), &[1.0, 2.0]);
+
+        calculator.apply_operation("redo").unwrap();
+        calculator.redo().unwrap();
+        assert_eq!(calculator.stack(), &[6.0]);
+        assert_eq!(calculator.infix(), "1 + 2 + 3");
+        assert_eq!(calculator.redo(), Err(CalcError::NothingToRedo));
+    }
+
+    #[test]
+    fn test_undo_limits() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(calculator.undo(), Err(CalcError::NothingToUndo));
+        calculator.evaluate_line("2 2 *").unwrap();
+        calculator.evaluate_line("undo undo").unwrap();
+        calculator.apply_operation("5").unwrap();
+        assert_eq!(calculator.redo(), Err(CalcError::NothingToRedo));
+        assert_eq!(calculator.stack(), &[2.0, 5.0]);
+        assert!(calculator.apply_operation("+").is_ok());
+        assert!(calculator.apply_operation("+").is_err());
+        calculator.undo().unwrap();
+        assert_eq!(calculator.stack(), &[2.0, 5.0]);
+    }
+
+    #[test]
+    fn test_stack_words() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2 3 rot").unwrap();
+        assert_eq!(calculator.stack(), &[2.0, 3.0, 1.0]);
+        calculator.evaluate_line("swap over").unwrap();
+        assert_eq!(calculator.stack(), &[2.0, 1.0, 3.0, 1.0]);
+        calculator.evaluate_line("drop 3 pick").unwrap();
+        assert_eq!(calculator.stack(), &[2.0, 1.0, 3.0, 2.0]);
+        calculator.evaluate_line("4 roll").unwrap();
+        assert_eq!(calculator.stack(), &[1.0, 3.0, 2.0, 2.0]);
+        calculator.evaluate_line("dup clear").unwrap();
+        assert!(calculator.stack().is_empty());
+    }
+
+    #[test]
+    fn test_stack_word_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2").unwrap();
+        assert_eq!(
+            calculator.apply_operation("rot"),
+            Err(CalcError::StackUnderflow { token: "rot".to_owned(), required: 3, available: 2 })
+        );
+        assert!(matches!(calculator.evaluate_line("0 pick"), Err(CalcError::DomainError(_))));
+        assert!(matches!(calculator.evaluate_line("5 roll"), Err(CalcError::StackUnderflow { .. })));
+        assert_eq!(calculator.stack(), &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_stack_words_reconstruction() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("3 dup *").unwrap();
+        assert_eq!(calculator.infix(), "3 * 3");
+        calculator.evaluate_line("4 5 swap - 2 pick +").unwrap();
+        assert_eq!(calculator.infix(), "5 - 4 + 3 * 3");
+        assert_eq!(calculator.latex(), r"5 - 4 + 3 \cdot 3");
+        calculator.evaluate_line("drop 1 2 roll /").unwrap();
+        assert_eq!(calculator.infix(), "1 / (3 * 3)");
+    }
+
+    #[test]
+    fn test_evaluate_infix() {
+        let mut calculator = RPNCalculator::new();
+        let tokens = calculator.evaluate_infix("(5 + 3) * sqrt(16)").unwrap();
+        assert_eq!(tokens.join(" "), "5 3 + 16 sqrt *");
+        assert_eq!(calculator.get_result(), Some(Value::from(32.0)));
+        assert_eq!(calculator.infix(), "(5 + 3) * sqrt(16)");
+        calculator.evaluate_line("=a").unwrap();
+        calculator.evaluate_infix("-a / 2 ^ 2").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(-8.0)));
+        calculator.evaluate_infix("y + 1").unwrap_err();
+        assert_eq!(calculator.stack(), vec![-8.0]);
+    }
+
+    #[test]
+    fn test_complex_numbers() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("-4 sqrt").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Complex(Complex::new(0.0, 2.0))));
+        calculator.evaluate_line("3+4i *").unwrap();
+        assert_eq!(calculator.stack.last().unwrap().value.to_string(), "-8+6i");
+        assert_eq!(calculator.infix(), "sqrt(-4) * (3+4i)");
+        assert_eq!(calculator.latex(), r"\sqrt{-4} \cdot \left(3 + 4i\right)");
+        calculator.evaluate_line("dup conj *").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(100.0)));
+
+        calculator.evaluate_line("clear 3 4 cplx dup abs swap dup re swap dup im swap arg").unwrap();
+        assert_eq!(calculator.stack(), vec![5.0, 3.0, 4.0, 4f64.atan2(3.0)]);
+        calculator.evaluate_line("clear i 2 ^ -1 ln 1 i / 8 log2 -8 1 3 / ^ abs").unwrap();
+        let pi = std::f64::consts::PI;
+        assert_eq!(calculator.stack()[..3], [Value::from(-1.0), Complex::new(0.0, pi).into(), Complex::new(0.0, -1.0).into()]);
+        assert_eq!(calculator.stack()[3], 3.0);
+        assert!((calculator.stack()[4].to_f64() - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_complex_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("2+i").unwrap();
+        for token in ["sin", "floor", "!", "pick"] {
+            assert_eq!(
+                calculator.apply_operation(token),
+                Err(CalcError::DomainError(format!("'{}' is not defined for the complex number 2+1i", token)))
+            );
+        }
+        calculator.evaluate_line("2").unwrap();
+        assert!(matches!(calculator.apply_operation("mod"), Err(CalcError::DomainError(_))));
+        assert!(matches!(calculator.apply_operation("max"), Err(CalcError::DomainError(_))));
+        calculator.evaluate_line("0 ln").unwrap_err();
+        calculator.evaluate_line("0 -1 i + ^").unwrap_err();
+        assert_eq!(calculator.stack(), vec![Value::from(Complex::new(2.0, 1.0)), Value::from(2.0)]);
+    }
+
+    #[test]
+    fn test_vectors_and_matrices() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact [1 2 3] [4, 5, 6] dot").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "32");
+        assert_eq!(calculator.infix(), "dot([1, 2, 3], [4, 5, 6])");
+        calculator.evaluate_line("drop [[1 2] [3 4]] inv 2 *").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "[[-4 2] [3 -1]]");
+        assert_eq!(
+            calculator.latex(),
+            r"\begin{pmatrix} 1 & 2 \\ 3 & 4 \end{pmatrix}^{-1} \cdot 2"
+        );
+        calculator.evaluate_line("clear [1 0 0] [0 1 0] cross [1 2 3] + ++").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "7");
+        assert_eq!(calculator.infix(), "sum(cross([1, 0, 0], [0, 1, 0]) + [1, 2, 3])");
+        assert!(calculator.latex().starts_with(r"\sum\left(\begin{pmatrix} 1 \\ 0 \\ 0 \end{pmatrix} \times"));
+        calculator.evaluate_line("clear [2 3] ** [[1 2] [3 4]] det [3 4] norm [1 2] transpose").unwrap();
+        let stack: Vec<String> = calculator.stack().iter().map(Value::to_string).collect();
+        assert_eq!(stack, ["6", "-2", "5", "[[1] [2]]"]);
+        calculator.evaluate_line("clear : twice [2 2] * ; [1 2] twice").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "[2 4]");
+
+        calculator.evaluate_line("float [0.5 1+2i] =v $v").unwrap();
+        let text = calculator.save_session();
+        assert!(text.contains("var v array [float:0.5,complex:1+2i]\n"));
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&text).unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+    }
+
+    #[test]
+    fn test_array_errors() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(calculator.evaluate_line("[1 2"), Err(CalcError::UnknownToken("[1 2".to_owned())));
+        assert_eq!(calculator.evaluate_line("[[1 2] [3]]"), Err(CalcError::UnknownToken("[[1 2] [3]]".to_owned())));
+        assert_eq!(calculator.evaluate_line("[1 x]"), Err(CalcError::UnknownToken("[1,x]".to_owned())));
+        let nested = format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(calculator.evaluate_line(&nested), Err(CalcError::UnknownToken(nested.clone())));
+        calculator.evaluate_line("[1 2]").unwrap();
+        assert_eq!(
+            calculator.apply_operation("sqrt"),
+            Err(CalcError::DomainError("'sqrt' is not defined for the vector [1 2]".to_owned()))
+        );
+        calculator.evaluate_line("[1 2 3]").unwrap();
+        assert_eq!(
+            calculator.apply_operation("+"),
+            Err(CalcError::DomainError("'+' cannot combine a 2-vector and a 3-vector".to_owned()))
+        );
+        assert!(matches!(calculator.apply_operation("det"), Err(CalcError::DomainError(_))));
+        assert_eq!(calculator.stack().len(), 2);
+    }
+
+    #[test]
+    fn test_number_bases_and_bitwise_operators() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact 0xff 0b1010 and 0o7 or").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("15").unwrap())));
+        assert_eq!(calculator.infix(), "or(and(0xff, 0b1010), 0o7)");
+        calculator.evaluate_line("hex 1 4 shl xor").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "0x1f");
+        calculator.evaluate_line("not -0x2 and 2 shr").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "-0x8");
+        assert_eq!(calculator.infix(), "shr(and(not(xor(or(and(0xff, 0b1010), 0o7), shl(1, 4))), -0x2), 2)");
+        assert!(calculator.latex().starts_with(r"\operatorname{shr}\left(\operatorname{and}\left(\operatorname{not}\left("));
+        assert!(calculator.latex().ends_with(r"-\texttt{0x2}\right), 2\right)"));
+
+        calculator.evaluate_line("clear float 0x10 2.5").unwrap();
+        assert_eq!(calculator.stack(), vec![Value::from(16.0), Value::from(2.5)]);
+        assert_eq!(
+            calculator.apply_operation("xor"),
+            Err(CalcError::DomainError("'xor' needs integers that fit into 128 bits, got 2.5".to_owned()))
+        );
+        calculator.evaluate_line("drop 1").unwrap();
+        assert!(matches!(calculator.apply_operation("shl"), Ok(())));
+        assert_eq!(calculator.get_result(), Some(Value::from(32.0)));
+        assert!(calculator.evaluate_line("1 127 shl").is_err());
+        assert!(calculator.evaluate_line("1 128 shr").is_err());
+        assert_eq!(calculator.evaluate_line("0b12"), Err(CalcError::UnknownToken("0b12".to_owned())));
+    }
+
+    #[test]
+    fn test_display_formats() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("2 3 / 1+2i").unwrap();
+        calculator.evaluate_line("fix 3").unwrap();
+        let shown: Vec<String> = calculator.stack().iter().map(|value| calculator.format_value(value)).collect();
+        assert_eq!(shown, ["0.667", "1.000+2.000i"]);
+        assert_eq!(calculator.number_format(), NumberFormat::Fixed(3));
+        calculator.evaluate_line("drop 1500 * sci 2").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "1.00e3");
+        calculator.evaluate_line("eng 1").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "1.0e3");
+        // Only the display changes
+        assert_eq!(calculator.get_result(), Some(Value::from(2.0 / 3.0 * 1500.0)));
+        calculator.evaluate_line("std").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "1000");
+
+        assert_eq!(calculator.evaluate_line("fix"), Err(CalcError::MissingArgument("fix".to_owned())));
+        assert!(calculator.evaluate_line("sci x").is_err());
+        assert!(calculator.evaluate_line("fix 21").is_err());
+        assert!(calculator.evaluate_line(": f fix 2 ;").is_err());
+        assert_eq!(calculator.number_format(), NumberFormat::Standard);
+
+        calculator.evaluate_line("oct eng 3").unwrap();
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&calculator.save_session()).unwrap();
+        assert_eq!(loaded.radix(), Radix::Octal);
+        assert_eq!(loaded.number_format(), NumberFormat::Engineering(3));
+    }
+
+    #[test]
+    fn test_units() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact 3 km 2 h /").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "3/2 km/h");
+        calculator.evaluate_line("to m/s").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "5/12 m/s");
+        assert_eq!(calculator.infix(), "3 km / (2 h) to m/s");
+        assert_eq!(
+            calculator.latex(),
+            r"\frac{3\,\mathrm{km}}{2\,\mathrm{h}} \rightarrow \frac{\mathrm{m}}{\mathrm{s}}"
+        );
+
+        calculator.evaluate_line("clear 2 m 2 ^ 50 cm *").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "200 m^2*cm");
+        calculator.evaluate_line("to L").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "2000 L");
+        calculator.evaluate_line("clear 1 km 500 m + 250 m /").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("6").unwrap())));
+        calculator.evaluate_line("clear 2 kg 3 m * 4 s s /").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "3/2 kg*m/s^2");
+        calculator.evaluate_line("to N").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "3/2 N");
+
+        calculator.evaluate_line(": speed to km/h ; clear float 10 m/s speed").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "36 km/h");
+        calculator.evaluate_line("fix 1").unwrap();
+        assert_eq!(calculator.format_value(&calculator.get_result().unwrap()), "36.0 km/h");
+
+        // Minutes can be converted to, while 'min' on its own is still the minimum
+        calculator.evaluate_line("std clear exact 1 h to min").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "60 min");
+        assert_eq!(calculator.infix(), "1 h to min");
+        calculator.evaluate_line("->s").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "3600 s");
+        calculator.evaluate_line("clear 4 2 min").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("2").unwrap())));
+    }
+
+    #[test]
+    fn test_unit_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("3 m 2 s").unwrap();
+        assert_eq!(
+            calculator.apply_operation("+"),
+            Err(CalcError::IncompatibleUnits("m".to_owned(), "s".to_owned()))
+        );
+        assert_eq!(
+            calculator.evaluate_line("to kg"),
+            Err(CalcError::IncompatibleUnits("s".to_owned(), "kg".to_owned()))
+        );
+        assert_eq!(calculator.evaluate_line("to"), Err(CalcError::MissingArgument("to".to_owned())));
+        assert_eq!(calculator.evaluate_line("to parsec"), Err(CalcError::UnknownToken("parsec".to_owned())));
+        assert_eq!(
+            calculator.apply_operation("sqrt"),
+            Err(CalcError::DomainError("'sqrt' is not defined for the quantity 2 s".to_owned()))
+        );
+        assert!(calculator.evaluate_line("1 +").is_err());
+        assert!(calculator.evaluate_line("0.5 ^").is_err());
+        assert!(calculator.evaluate_line("[1 2] m").is_err());
+        assert!(calculator.evaluate_line("[1 2] *").is_err());
+        assert_eq!(calculator.stack().len(), 2);
+
+        let text = calculator.save_session();
+        assert!(text.contains("entry quantity float:2:s = 2 s\n"));
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&text).unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact 2 4 4 4 5 5 7 9 mean").unwrap();
+        assert_eq!(calculator.stack().len(), 1);
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("5").unwrap())));
+        assert_eq!(calculator.infix(), "mean(2, 4, 4, 4, 5, 5, 7, 9)");
+        calculator.evaluate_line("undo stddev").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "2.138089935299395");
+        calculator.evaluate_line("clear 1 3 5 stddev").unwrap();
+        assert_eq!(calculator.latex(), r"\sigma\left(1, 3, 5\right)");
+        calculator.evaluate_line("clear float 3 1 2 + 7 median").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(3.0)));
+        calculator.evaluate_line("clear 3 1 2 smin").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(1.0)));
+        calculator.evaluate_line("6 smax 4 count").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("2").unwrap())));
+        // 'min' and 'max' still compare the top two values
+        calculator.evaluate_line("clear 5 1 2 min").unwrap();
+        assert_eq!(calculator.stack(), vec![5.0, 1.0]);
+        calculator.evaluate_line("3 max").unwrap();
+        assert_eq!(calculator.stack(), vec![5.0, 3.0]);
+
+        calculator.evaluate_line("clear exact 1 3 2 5 3 7 linreg").unwrap();
+        let values: Vec<String> = calculator.stack().iter().map(Value::to_string).collect();
+        assert_eq!(values, ["1", "2"]);
+        assert_eq!(calculator.infix(), "slope(1, 3, 2, 5, 3, 7)");
+
+        let text = calculator.save_session();
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&text).unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+    }
+
+    #[test]
+    fn test_statistics_errors() {
+        let mut calculator = RPNCalculator::new();
+        assert!(matches!(calculator.apply_operation("mean"), Err(CalcError::StackUnderflow { .. })));
+        calculator.evaluate_line("1").unwrap();
+        assert!(matches!(calculator.apply_operation("var"), Err(CalcError::StackUnderflow { .. })));
+        calculator.evaluate_line("2").unwrap();
+        assert!(matches!(calculator.apply_operation("linreg"), Err(CalcError::StackUnderflow { .. })));
+        let pairs = |count| format!("'linreg' needs an even number of values (x/y pairs), got {}", count);
+        calculator.evaluate_line("3").unwrap();
+        assert_eq!(calculator.apply_operation("linreg"), Err(CalcError::DomainError(pairs(3))));
+        calculator.evaluate_line("4 5").unwrap();
+        assert_eq!(calculator.apply_operation("linreg"), Err(CalcError::DomainError(pairs(5))));
+        assert!(calculator.evaluate_line("clear 1 4 1 5 linreg").is_err());
+        assert_eq!(calculator.stack().len(), 5);
+        assert_eq!(
+            calculator.evaluate_line("3+4i mean"),
+            Err(CalcError::DomainError("'mean' is not defined for the complex number 3+4i".to_owned()))
+        );
+        assert!(calculator.evaluate_line("[1 2] mean").is_err());
+        assert!(calculator.evaluate_line("2 m count").is_err());
+        assert_eq!(calculator.stack().len(), 5);
+
+        // There are no values on an empty stack
+        calculator.evaluate_line("clear count").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("0").unwrap())));
+        assert_eq!((calculator.infix(), calculator.latex()), ("count()".to_owned(), r"n\left(\right)".to_owned()));
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&calculator.save_session()).unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("x 2 ^ 3 x * + 1 +").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "x ^ 2 + 3 * x + 1");
+        calculator.evaluate_line("dup at 2").unwrap();
+        assert_eq!(calculator.stack()[1], Value::from(11.0));
+        assert_eq!(calculator.infix(), "x ^ 2 + 3 * x + 1 at 2");
+        calculator.evaluate_line("drop diff").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "2 * x + 3");
+        assert_eq!(calculator.infix(), "diff(x ^ 2 + 3 * x + 1)");
+        calculator.evaluate_line("diff").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(2.0)));
+
+        calculator.evaluate_line("clear exact x x - 1 x / * simp").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("0").unwrap())));
+        calculator.evaluate_line("clear x sin x * diff").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "cos(x) * x + sin(x)");
+        calculator.evaluate_line("at 0").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(0.0)));
+        calculator.evaluate_line("clear x 1 3 / * at 1/2").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("1/6").unwrap())));
+        calculator.evaluate_line("clear deg x sin diff at 0").unwrap();
+        assert!((calculator.get_result().unwrap().to_f64() - std::f64::consts::PI / 180.0).abs() < 1e-15);
+        calculator.evaluate_line("clear rad x 2 * 3 x * + simp").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "5 * x");
+
+        // The printed infix can be entered again, only constant parts like ln(2) are evaluated then
+        calculator.evaluate_line("clear x abs x log2 + x asin x acos * - x atan / diff").unwrap();
+        let infix = calculator.get_result().unwrap().to_string();
+        calculator.evaluate_line("at 0.5").unwrap();
+        let expected = calculator.get_result().unwrap().to_f64();
+        calculator.evaluate_line("clear").unwrap();
+        calculator.evaluate_infix(&infix).unwrap();
+        calculator.evaluate_line("at 0.5").unwrap();
+        assert!((calculator.get_result().unwrap().to_f64() - expected).abs() < 1e-12);
+
+        // Expressions survive storing, words and sessions
+        calculator.evaluate_line(": slope diff at 1 ; clear rad x 3 ^ =f $f slope $f").unwrap();
+        assert_eq!(calculator.stack()[0], Value::Exact(Rational::parse("3").unwrap()));
+        let text = calculator.save_session();
+        assert!(text.contains("var f symbolic x,3,^\n"));
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&text).unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+        assert_eq!(loaded.variables().collect::<Vec<_>>(), calculator.variables().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_symbolic_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("x").unwrap();
+        assert_eq!(
+            calculator.apply_operation("floor"),
+            Err(CalcError::DomainError("'floor' is not defined for the expression x".to_owned()))
+        );
+        assert!(calculator.evaluate_line("2 max").is_err());
+        assert!(calculator.evaluate_line("[1 2] *").is_err());
+        assert!(calculator.evaluate_line("m").is_err());
+        assert!(calculator.evaluate_line("x mean").is_err());
+        assert_eq!(calculator.evaluate_line("at"), Err(CalcError::MissingArgument("at".to_owned())));
+        assert_eq!(
+            calculator.evaluate_line("at y"),
+            Err(CalcError::DomainError("'at' needs a number, got 'y'".to_owned()))
+        );
+        assert!(calculator.evaluate_line("ln at 0").is_err());
+        assert_eq!(calculator.stack().len(), 1);
+        assert_eq!(calculator.define("x", &["1"]), Err(CalcError::InvalidName("x".to_owned())));
+    }
+
+    #[test]
+    fn test_plot() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact x 2 ^").unwrap();
+        let plot = calculator.plot(-1.0, 1.0, 5).unwrap();
+        assert_eq!(plot.title(), "x ^ 2");
+        assert_eq!(plot.points(), [(-1.0, 1.0), (-0.5, 0.25), (0.0, 0.0), (0.5, 0.25), (1.0, 1.0)]);
+        calculator.evaluate_line("clear x ln").unwrap();
+        let plot = calculator.plot(-1.0, 1.0, 3).unwrap();
+        assert!(plot.points()[0].1.is_nan());
+        assert_eq!(plot.points()[2], (1.0, 0.0));
+        calculator.evaluate_line("clear 3").unwrap();
+        assert_eq!(calculator.plot(0.0, 1.0, 2).unwrap().points(), [(0.0, 3.0), (1.0, 3.0)]);
+
+        assert!(calculator.plot(1.0, 1.0, 10).is_err());
+        // The bounds are printed in the current number format
+        calculator.evaluate_line("sci 1").unwrap();
+        assert_eq!(
+            calculator.plot(-1e308, 1e308, 10),
+            Err(CalcError::DomainError(
+                "'plot' needs a finite range, got -1.0e308 and 1.0e308".to_owned()
+            ))
+        );
+        calculator.evaluate_line("std").unwrap();
+        assert!(calculator.evaluate_line("clear x 1 + sqrt 2 - ln").is_ok());
+        assert_eq!(
+            calculator.plot(-3.0, -2.0, 10),
+            Err(CalcError::DomainError("'plot' found no real values between -3 and -2".to_owned()))
+        );
+        calculator.evaluate_line("clear [1 2]").unwrap();
+        assert!(calculator.plot(0.0, 1.0, 10).is_err());
+        calculator.evaluate_line("clear").unwrap();
+        assert!(matches!(calculator.plot(0.0, 1.0, 10), Err(CalcError::StackUnderflow { .. })));
+    }
+
+    #[test]
+    fn test_completions() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line(": sq dup * ; 2 =rate").unwrap();
+        let completions = calculator.completions();
+        assert!(completions.iter().any(|word| word == "atan2"));
+        assert_eq!(completions[completions.len() - 3..], ["sq", "$rate", "=rate"]);
+    }
+
+    #[test]
+    fn test_save_and_load_session() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line(": sq dup * ; 0.1 =a exact 1/3 =b deg 2 $b + 3 sq sin").unwrap();
+        let text = calculator.save_session();
+        assert_eq!(
+            text,
+            "# RPN calculator session\nmode exact\nangle deg\nradix dec\nformat std\nword sq dup *\nvar a float 0.1\n\
+             var b exact 1/3\nentry exact 7/3 = 2 $b +\nentry float 0.15643446504023087 = 3 3 * sin\n"
+        );
+
+        let mut loaded = RPNCalculator::new();
+        loaded.load_session(&text).unwrap();
+        assert_eq!(loaded.save_session(), text);
+        assert_eq!(loaded.entries(), calculator.entries());
+        assert_eq!(loaded.infix(), "sin(3 * 3)");
+        loaded.evaluate_line("drop 4 sq $a +").unwrap();
+        assert_eq!(loaded.stack(), vec![Value::Exact(Rational::parse("7/3").unwrap()), Value::from(16.1)]);
+        loaded.evaluate_line("undo undo undo undo undo").unwrap();
+        assert_eq!(loaded.entries(), calculator.entries());
+        assert_eq!(loaded.undo(), Err(CalcError::NothingToUndo));
+        // The token list of older sessions is skipped
+        loaded.load_session(&format!("{}history 2 $b + 3 dup * sin\n", text)).unwrap();
+        assert_eq!(loaded.save_session(), text);
+    }
+
+    #[test]
+    fn test_load_session_errors() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2").unwrap();
+        let error = |line: usize, message: &str| {
+            Err(CalcError::InvalidSession { line, message: message.to_owned() })
+        };
+        assert_eq!(calculator.load_session("mode exact\nmode fast\n"), error(2, "cannot read 'mode fast'"));
+        assert_eq!(calculator.load_session("var x exact 1/0"), error(1, "invalid value 'exact 1/0'"));
+        assert_eq!(calculator.load_session("entry float 1 = 1 +"), error(1, "invalid expression '1 +'"));
+        assert_eq!(calculator.load_session("word dup 1"), error(1, "Invalid name 'dup'"));
+        assert_eq!(calculator.stack(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_expression_per_entry() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("1 2 3 + pi 2 / sin").unwrap();
+        let infix: Vec<String> = calculator.entries().iter().map(|entry| entry.expr.to_infix()).collect();
+        assert_eq!(infix, vec!["1", "2 + 3", "sin(pi / 2)"]);
+        assert_eq!(calculator.stack(), vec![1.0, 5.0, 1.0]);
+        calculator.evaluate_line("rot undo swap").unwrap();
+        assert_eq!(calculator.entries()[1].expr.to_latex(), r"\sin\left(\frac{\pi}{2}\right)");
+        assert_eq!(calculator.infix(), "2 + 3");
+        calculator.evaluate_line("++").unwrap();
+        assert_eq!(calculator.infix(), "1 + sin(pi / 2) + (2 + 3)");
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("5 3 + sto a 2 =b").unwrap();
+        assert!(calculator.stack().is_empty());
+        calculator.evaluate_line("rcl a $b * $a +").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(24.0)));
+        assert_eq!(
+            calculator.variables().map(|(name, value)| (name, value.to_f64())).collect::<Vec<_>>(),
+            vec![("a", 8.0), ("b", 2.0)]
+        );
+        assert_eq!(calculator.infix(), "a * b + a");
+        calculator.evaluate_line("$rate +").unwrap_err();
+        calculator.evaluate_line("=rate $rate $rate *").unwrap();
+        assert_eq!(calculator.latex(), r"\mathrm{rate} \cdot \mathrm{rate}");
+    }
+
+    #[test]
+    fn test_variable_errors() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(calculator.evaluate_line("$x"), Err(CalcError::UnknownVariable("x".to_owned())));
+        assert_eq!(calculator.evaluate_line("1 =2x"), Err(CalcError::InvalidName("2x".to_owned())));
+        assert_eq!(calculator.evaluate_line("1 sto"), Err(CalcError::MissingArgument("sto".to_owned())));
+        assert!(matches!(calculator.evaluate_line("=x"), Err(CalcError::StackUnderflow { .. })));
+        calculator.evaluate_line("4 =x").unwrap();
+        calculator.undo().unwrap();
+        assert_eq!(calculator.variables().count(), 0);
+    }
+
+    #[test]
+    fn test_definitions() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line(": hyp dup * swap dup * + sqrt ;").unwrap();
+        calculator.evaluate_line("3 4 hyp").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(5.0)));
+        assert_eq!(calculator.infix(), "sqrt(4 * 4 + 3 * 3)");
+
+        // A whole word is undone at once
+        calculator.undo().unwrap();
+        assert_eq!(calculator.stack(), &[3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_redefinition() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line(": sq dup * ; : quad sq sq ; : keep sto k ;").unwrap();
+        calculator.evaluate_line(": sq 2 * ;").unwrap();
+        calculator.evaluate_line("2 quad 3 sq").unwrap();
+        assert_eq!(calculator.stack(), &[16.0, 6.0]);
+        assert_eq!(
+            calculator.definitions().map(|(name, body)| format!("{}: {}", name, body.join(" "))).collect::<Vec<_>>(),
+            vec!["keep: =k", "quad: dup * dup *", "sq: 2 *"]
+        );
+        assert!(calculator.is_defined("quad"));
+    }
+
+    #[test]
+    fn test_definition_errors() {
+        let mut calculator = RPNCalculator::new();
+        assert_eq!(calculator.evaluate_line(": dup 2 * ;"), Err(CalcError::InvalidName("dup".to_owned())));
+        assert_eq!(calculator.evaluate_line(": f 2 * "), Err(CalcError::UnterminatedDefinition("f".to_owned())));
+        assert_eq!(
+            calculator.evaluate_line(": f 2 * ; : g 3"),
+            Err(CalcError::UnterminatedDefinition("g".to_owned()))
+        );
+        assert_eq!(calculator.evaluate_line(": f q ;"), Err(CalcError::UnknownToken("q".to_owned())));
+        assert!(matches!(calculator.evaluate_line(": f undo ;"), Err(CalcError::InvalidDefinition(_))));
+        assert!(matches!(calculator.evaluate_line(": f f ;"), Err(CalcError::UnknownToken(_))));
+        assert_eq!(calculator.definitions().count(), 0);
+    }
+
+    #[test]
+    fn test_exact_mode() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("0.1 0.2 +").unwrap();
+        assert_ne!(calculator.get_result(), Some(Value::from(0.3)));
+
+        calculator.evaluate_line("clear exact 0.1 0.2 +").unwrap();
+        assert_eq!(calculator.mode(), NumberMode::Exact);
+        assert_eq!(calculator.get_result().unwrap().to_string(), "3/10");
+        calculator.evaluate_line("3 / 1/10 -").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Exact(Rational::parse("0").unwrap())));
+
+        calculator.evaluate_line("clear 2 100 ^ 3 -2 ^ 16/9 sqrt").unwrap();
+        let stack: Vec<String> = calculator.stack().iter().map(|value| value.to_string()).collect();
+        assert_eq!(stack, vec!["1267650600228229401496703205376", "1/9", "4/3"]);
+    }
+
+    #[test]
+    fn test_exact_mode_fallback() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact 2 sqrt 2 0.5 ^ 10 log").unwrap();
+        assert!(calculator.stack().iter().all(|value| !value.is_exact()));
+        calculator.evaluate_line("clear 1 float 0.5 +").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::Float(1.5)));
+        calculator.set_mode(NumberMode::Exact).unwrap();
+        assert!(matches!(calculator.evaluate_line("1 0 /"), Err(CalcError::DomainError(_))));
+        assert!(matches!(calculator.evaluate_line("0 -1 ^"), Err(CalcError::DomainError(_))));
+    }
+
+    #[test]
+    fn test_exact_factorial() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("exact 25 !").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "15511210043330985984000000");
+        assert_eq!(calculator.evaluate_line("1/2 !"), Err(CalcError::InvalidFactorial(0.5)));
+        assert_eq!(calculator.evaluate_line("10001 !"), Err(CalcError::FactorialOverflow(10001.0)));
+    }
+
+    #[test]
+    fn test_trigonometry() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("pi 2 / sin").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(1.0)));
+        assert_eq!(calculator.infix(), "sin(pi / 2)");
+        assert_eq!(calculator.latex(), r"\sin\left(\frac{\pi}{2}\right)");
+
+        calculator.evaluate_line("clear deg 60 cos 1 1 atan2").unwrap();
+        assert_eq!(calculator.angle_mode(), AngleMode::Degrees);
+        assert!((calculator.stack()[0].to_f64() - 0.5).abs() < 1e-12);
+        assert!((calculator.stack()[1].to_f64() - 45.0).abs() < 1e-12);
+        calculator.evaluate_line("clear rad 1 acos").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(0.0)));
+        assert!(matches!(calculator.evaluate_line("2 asin"), Err(CalcError::DomainError(_))));
+    }
+
+    #[test]
+    fn test_exponential_and_constants() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("e ln 8 log2 1 exp").unwrap();
+        assert_eq!(calculator.stack(), &[1.0, 3.0, std::f64::consts::E]);
+        assert_eq!(calculator.infix(), "exp(1)");
+        assert_eq!(calculator.latex(), "e^{1}");
+        assert!(matches!(calculator.evaluate_line("0 ln"), Err(CalcError::DomainError(_))));
+    }
+
+    #[test]
+    fn test_rounding_and_binary_functions() {
+        let mut calculator = RPNCalculator::new();
+        calculator.evaluate_line("-2.5 floor -2.5 ceil -2.5 round -7 3 mod 7 -3 mod 4 2 min 4 2 max").unwrap();
+        assert_eq!(calculator.stack(), &[-3.0, -2.0, -3.0, 2.0, -2.0, 2.0, 4.0]);
+        assert_eq!(calculator.infix(), "max(4, 2)");
+        assert!(matches!(calculator.evaluate_line("1 0 mod"), Err(CalcError::DomainError(_))));
+
+        calculator.evaluate_line("clear exact -5/2 round 7/2 floor 22/7 1/2 mod 1/3 0.5 min").unwrap();
+        let stack: Vec<String> = calculator.stack().iter().map(|value| value.to_string()).collect();
+        assert_eq!(stack, vec!["-3", "3", "1/7", "1/3"]);
+        assert_eq!(calculator.latex(), r"\min\left(\frac{1}{3}, 0.5\right)");
+    }
+
+    #[test]
+    fn test_config_operators() {
+        let config = "[hypot]\narity = 2\nrpn = dup * swap dup * + sqrt\n\n\
+                      [pct]\narity = 2\nrpn = * 100 /\ninfix = $0 % $1\nlatex = $0 \\% $1\n\
+                      precedence = product\n\n\
+                      [pair]\narity = 1\nrpn = dup\n";
+        let mut calculator = RPNCalculator::new();
+        calculator.load_operators(config).unwrap();
+        calculator.evaluate_line("3 4 hypot").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(5.0)));
+        assert_eq!(calculator.infix(), "hypot(3, 4)");
+        assert_eq!(calculator.latex(), r"\operatorname{hypot}\left(3, 4\right)");
+        calculator.evaluate_line("clear 1 2 + 50 pct 2 *").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(3.0)));
+        assert_eq!(calculator.infix(), "(1 + 2) % 50 * 2");
+        assert_eq!(calculator.latex(), r"\left(1 + 2\right) \% 50 \cdot 2");
+        calculator.evaluate_line("clear").unwrap();
+        calculator.evaluate_infix("hypot(5, 12) + 1").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(14.0)));
+        // Infix input reads their symbols with the precedence from the config file
+        calculator.evaluate_line("clear").unwrap();
+        calculator.evaluate_infix("1 + 2 % 50 * 2").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(3.0)));
+        assert_eq!(calculator.infix(), "1 + 2 % 50 * 2");
+
+        // Errors leave the stack as it was
+        calculator.evaluate_line("clear 1").unwrap();
+        assert!(matches!(calculator.evaluate_line("hypot"), Err(CalcError::StackUnderflow { .. })));
+        let message = "'pair' has to leave exactly one value, but left 2".to_owned();
+        assert_eq!(calculator.evaluate_line("pair"), Err(CalcError::DomainError(message)));
+        assert_eq!(calculator.stack(), &[1.0]);
+        assert!(calculator.define("hypot", &["1"]).is_err());
+
+        // Loaded sessions and expressions of x keep the operators, other calculators do not have them
+        calculator.evaluate_line("clear x 4 hypot").unwrap();
+        assert_eq!(calculator.infix(), "hypot(x, 4)");
+        calculator.evaluate_line("at 3").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(5.0)));
+        let session = calculator.save_session();
+        calculator.load_session(&session).unwrap();
+        assert_eq!(calculator.infix(), "hypot(x, 4) at 3");
+        assert!(RPNCalculator::new().evaluate_line("3 4 hypot").is_err());
+
+        // Their operands are not checked, even under the name of an operator that is only written
+        let mut calculator = RPNCalculator::new();
+        calculator.load_operators("[sum]\narity = 1\nrpn = ++\n\n[double]\narity = 1\nrpn = 2 *\n").unwrap();
+        calculator.evaluate_line("[1 2 3] sum").unwrap();
+        assert_eq!(calculator.get_result(), Some(Value::from(6.0)));
+        calculator.evaluate_line("clear 3 m double").unwrap();
+        assert_eq!(calculator.get_result().unwrap().to_string(), "6 m");
+    }
+}
@@ -0,0 +1,194 @@
+// Library for implementing the display
+use std::fmt;
+
+// Complex number with floating point parts, entered as "3+4i" or with 'cplx'
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+// Integer powers up to this size are calculated by multiplying, so i^2 is exactly -1
+const MAX_MULTIPLIED_POWER: f64 = 1024.0;
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn from_real(re: f64) -> Self {
+        Complex { re, im: 0.0 }
+    }
+
+    // Parses "3+4i", "-2.5-1e3i", "4i" and "-i"
+    pub fn parse(text: &str) -> Option<Self> {
+        let (real, imaginary) = split_parts(text)?;
+        let re = if real.is_empty() { 0.0 } else { real.parse::<f64>().ok()? };
+        let im = match imaginary {
+            "" | "+" => 1.0,
+            "-" => -1.0,
+            _ => imaginary.parse::<f64>().ok()?,
+        };
+        Some(Complex { re, im })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    // Angle to the positive real axis in radians, between -pi and pi
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex { re: self.re, im: -self.im }
+    }
+
+    pub fn add(&self, other: &Complex) -> Complex {
+        Complex { re: self.re + other.re, im: self.im + other.im }
+    }
+
+    pub fn sub(&self, other: &Complex) -> Complex {
+        Complex { re: self.re - other.re, im: self.im - other.im }
+    }
+
+    pub fn mul(&self, other: &Complex) -> Complex {
+        Complex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+
+    // None when dividing by zero
+    pub fn div(&self, other: &Complex) -> Option<Complex> {
+        if other.is_zero() {
+            return None;
+        }
+        let norm = other.re * other.re + other.im * other.im;
+        let numerator = self.mul(&other.conj());
+        Some(Complex { re: numerator.re / norm, im: numerator.im / norm })
+    }
+
+    pub fn exp(&self) -> Complex {
+        let length = self.re.exp();
+        Complex { re: length * self.im.cos(), im: length * self.im.sin() }
+    }
+
+    // Principal natural logarithm, None for zero
+    pub fn ln(&self) -> Option<Complex> {
+        if self.is_zero() {
+            return None;
+        }
+        Some(Complex { re: self.abs().ln(), im: self.arg() })
+    }
+
+    // Principal square root, its real part is never negative
+    pub fn sqrt(&self) -> Complex {
+        let length = self.abs();
+        let re = ((length + self.re) / 2.0).sqrt();
+        let im = ((length - self.re) / 2.0).sqrt();
+        Complex { re, im: if self.im < 0.0 { -im } else { im } }
+    }
+
+    // Principal power, None for zero to a power with a non-positive real part
+    pub fn pow(&self, exponent: &Complex) -> Option<Complex> {
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= MAX_MULTIPLIED_POWER {
+            let mut result = Complex::from_real(1.0);
+            let mut base = *self;
+            let mut remaining = exponent.re.abs() as u64;
+            while remaining > 0 {
+                if remaining % 2 == 1 {
+                    result = result.mul(&base);
+                }
+                base = base.mul(&base);
+                remaining /= 2;
+            }
+            return if exponent.re < 0.0 { Complex::from_real(1.0).div(&result) } else { Some(result) };
+        }
+        if self.is_zero() {
+            return (exponent.re > 0.0).then_some(Complex::from_real(0.0));
+        }
+        Some(exponent.mul(&self.ln()?).exp())
+    }
+}
+
+// Splits a complex literal into its real part and the signed coefficient of 'i',
+// e.g. "3-4i" into ("3", "-4") and "i" into ("", "")
+pub(crate) fn split_parts(text: &str) -> Option<(&str, &str)> {
+    let body = text.strip_suffix('i')?;
+    let bytes = body.as_bytes();
+    // The last sign that does not belong to an exponent separates the two parts
+    let separator = (1..bytes.len())
+        .rev()
+        .find(|&index| matches!(bytes[index], b'+' | b'-') && !matches!(bytes[index - 1], b'e' | b'E'));
+    match separator {
+        Some(index) => Some((&body[..index], &body[index..])),
+        None => Some(("", body)),
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.im.is_sign_negative() {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complex(text: &str) -> Complex {
+        Complex::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(complex("3+4i"), Complex::new(3.0, 4.0));
+        assert_eq!(complex("-2.5-1e3i"), Complex::new(-2.5, -1000.0));
+        assert_eq!(complex("1e-3+2E+2i"), Complex::new(0.001, 200.0));
+        assert_eq!(complex("4i"), Complex::new(0.0, 4.0));
+        assert_eq!(complex("-i"), Complex::new(0.0, -1.0));
+        assert_eq!(complex("2+i"), Complex::new(2.0, 1.0));
+        assert_eq!(Complex::parse("pi"), None);
+        assert_eq!(Complex::parse("3+4"), None);
+        assert_eq!(Complex::new(3.0, -4.0).to_string(), "3-4i");
+        assert_eq!(complex(&Complex::new(-0.5, 1e-20).to_string()), Complex::new(-0.5, 1e-20));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let z = complex("3+4i");
+        assert_eq!(z.add(&complex("1-i")), complex("4+3i"));
+        assert_eq!(z.mul(&z.conj()), Complex::from_real(25.0));
+        assert_eq!(z.div(&complex("2i")), Some(complex("2-1.5i")));
+        assert_eq!(z.div(&Complex::from_real(0.0)), None);
+        assert_eq!(z.abs(), 5.0);
+        assert_eq!(complex("i").pow(&Complex::from_real(2.0)), Some(Complex::from_real(-1.0)));
+        assert_eq!(complex("2i").pow(&Complex::from_real(-2.0)), Some(Complex::new(-0.25, 0.0)));
+        assert_eq!(Complex::from_real(0.0).pow(&Complex::new(0.5, 1.0)), Some(Complex::from_real(0.0)));
+        assert_eq!(Complex::from_real(0.0).pow(&Complex::from_real(-0.5)), None);
+    }
+
+    #[test]
+    fn test_roots_and_logarithms() {
+        assert_eq!(Complex::from_real(-4.0).sqrt(), complex("2i"));
+        assert_eq!(complex("3+4i").sqrt(), complex("2+i"));
+        assert_eq!(complex("-3-4i").sqrt(), complex("1-2i"));
+        let ln = Complex::from_real(-1.0).ln().unwrap();
+        assert_eq!(ln, Complex::new(0.0, std::f64::consts::PI));
+        assert_eq!(Complex::from_real(0.0).ln(), None);
+        let root = Complex::from_real(-8.0).pow(&Complex::from_real(1.0 / 3.0)).unwrap();
+        assert!((root.re - 1.0).abs() < 1e-12 && (root.im - 3f64.sqrt()).abs() < 1e-12);
+    }
+}
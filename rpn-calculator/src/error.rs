@@ -0,0 +1,94 @@
+// Library for implementing the error display
+use std::fmt;
+
+// Error type for every way a token can be rejected by the calculator
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    StackUnderflow { token: String, required: usize, available: usize },
+    UnknownToken(String),
+    DomainError(String),
+    InvalidFactorial(f64),
+    FactorialOverflow(f64),
+    NothingToUndo,
+    NothingToRedo,
+    UnknownVariable(String),
+    InvalidName(String),
+    MissingArgument(String),
+    UnterminatedDefinition(String),
+    InvalidDefinition(String),
+    InvalidExpression(String),
+    InvalidSession { line: usize, message: String },
+    InvalidConfig { line: usize, message: String },
+    IncompatibleUnits(String, String),
+}
+
+impl CalcError {
+    // Name of the variant, so callers can tell errors apart without parsing the message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CalcError::StackUnderflow { .. } => "StackUnderflow",
+            CalcError::UnknownToken(_) => "UnknownToken",
+            CalcError::DomainError(_) => "DomainError",
+            CalcError::InvalidFactorial(_) => "InvalidFactorial",
+            CalcError::FactorialOverflow(_) => "FactorialOverflow",
+            CalcError::NothingToUndo => "NothingToUndo",
+            CalcError::NothingToRedo => "NothingToRedo",
+            CalcError::UnknownVariable(_) => "UnknownVariable",
+            CalcError::InvalidName(_) => "InvalidName",
+            CalcError::MissingArgument(_) => "MissingArgument",
+            CalcError::UnterminatedDefinition(_) => "UnterminatedDefinition",
+            CalcError::InvalidDefinition(_) => "InvalidDefinition",
+            CalcError::InvalidExpression(_) => "InvalidExpression",
+            CalcError::InvalidSession { .. } => "InvalidSession",
+            CalcError::InvalidConfig { .. } => "InvalidConfig",
+            CalcError::IncompatibleUnits(_, _) => "IncompatibleUnits",
+        }
+    }
+}
+
+// Numbers from this size on are shown with an exponent, '{}' would print every digit of 1e300
+const LARGE_NUMBER: f64 = 1e15;
+
+fn short_number(a: f64) -> String {
+    if a.abs() >= LARGE_NUMBER { format!("{:e}", a) } else { a.to_string() }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::StackUnderflow { token, required, available } => write!(
+                f,
+                "Stack underflow: '{}' needs {} value(s), but only {} available",
+                token, required, available
+            ),
+            CalcError::UnknownToken(token) => write!(f, "Invalid input '{}'", token),
+            CalcError::DomainError(message) => write!(f, "Domain error: {}", message),
+            CalcError::InvalidFactorial(a) => {
+                write!(f, "Invalid input '{}', cannot calculate the factorial", short_number(*a))
+            }
+            CalcError::FactorialOverflow(a) => {
+                write!(f, "Factorial of '{}' is too large to be calculated", short_number(*a))
+            }
+            CalcError::NothingToUndo => write!(f, "Nothing to undo"),
+            CalcError::NothingToRedo => write!(f, "Nothing to redo"),
+            CalcError::UnknownVariable(name) => write!(f, "Unknown variable '{}'", name),
+            CalcError::InvalidName(name) => write!(f, "Invalid name '{}'", name),
+            CalcError::MissingArgument(token) => write!(f, "'{}' needs an argument", token),
+            CalcError::UnterminatedDefinition(name) => {
+                write!(f, "Definition of '{}' is missing the closing ';'", name)
+            }
+            CalcError::InvalidDefinition(message) => write!(f, "Invalid definition: {}", message),
+            CalcError::InvalidExpression(message) => write!(f, "Invalid expression: {}", message),
+            CalcError::InvalidSession { line, message } => {
+                write!(f, "Invalid session file, line {}: {}", line, message)
+            }
+            CalcError::InvalidConfig { line, message } => {
+                write!(f, "Invalid operator config, line {}: {}", line, message)
+            }
+            CalcError::IncompatibleUnits(a, b) => write!(f, "Incompatible units '{}' and '{}'", a, b),
+        }
+    }
+}
+
+// Lets the errors be returned as Box<dyn Error> and combined with other error types
+impl std::error::Error for CalcError {}
@@ -0,0 +1,385 @@
+// Expression tree of a calculation, printed as infix or LaTeX with only the parentheses that are needed
+use std::rc::Rc;
+
+use crate::complex::split_parts;
+use crate::format::parse_integer_literal;
+use crate::matrix::{parse_array, Array};
+use crate::operators::{
+    self, template_precedence, Arity, Operator, Operators, Piece, ATOM, CONVERSION, NEGATION, PRODUCT, SUM,
+};
+use crate::units::Unit;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    // Number exactly as it was typed, e.g. "-2.5", "1/3" or "3+4i", or a literal like "[[1,2],[3,4]]"
+    Number(String),
+    Variable(String),
+    // Named constant like pi or e, or the symbolic variable x
+    Constant(String),
+    // Operator token with its operands in stack order. The operands are shared, so copies of a
+    // stack entry for undo do not copy the whole tree
+    Operation(String, Rc<[Expr]>),
+}
+
+impl Expr {
+    pub fn operation(token: &str, operands: Vec<Expr>) -> Expr {
+        Expr::Operation(token.to_owned(), operands.into())
+    }
+
+    // Postfix tokens of the expression, variables are written as '$name' like on input
+    pub fn to_rpn(&self) -> Vec<String> {
+        match self {
+            Expr::Number(text) | Expr::Constant(text) => vec![text.clone()],
+            Expr::Variable(name) => vec![format!("${}", name)],
+            Expr::Operation(token, operands) => {
+                let mut tokens: Vec<String> = operands.iter().flat_map(Expr::to_rpn).collect();
+                tokens.push(token.clone());
+                tokens
+            }
+        }
+    }
+
+    // Rebuilds an expression from its postfix tokens, None if they do not form exactly one expression
+    pub fn from_rpn(tokens: &[&str]) -> Option<Expr> {
+        Expr::from_rpn_with(tokens, Operators::builtin())
+    }
+
+    // Like from_rpn, with the operators of a calculator
+    pub fn from_rpn_with(tokens: &[&str], operators: &Operators) -> Option<Expr> {
+        let mut expressions: Vec<Expr> = Vec::new();
+        for &token in tokens {
+            // Statistics take every expression before them, like they take the whole stack
+            let arity = match operators.written(token).map(|operator| operator.arity) {
+                Some(Arity::Fixed(arity)) => Some(arity).filter(|&arity| arity > 0),
+                Some(Arity::Stack(_)) => Some(expressions.len()),
+                None if unit_operation(token).is_some() || token.starts_with('@') => Some(1),
+                None => None,
+            };
+            let expr = match (arity, token) {
+                (Some(arity), _) => {
+                    let operands = expressions.split_off(expressions.len().checked_sub(arity)?);
+                    Expr::operation(token, operands)
+                }
+                (None, "pi" | "e" | "x") => Expr::Constant(token.to_owned()),
+                (None, _) => match token.strip_prefix('$') {
+                    Some(name) => Expr::Variable(name.to_owned()),
+                    None => Expr::Number(token.to_owned()),
+                },
+            };
+            expressions.push(expr);
+        }
+        if expressions.len() == 1 { expressions.pop() } else { None }
+    }
+
+    // Leaves can be printed next to anything without being mistaken for something else
+    fn is_leaf(&self) -> bool {
+        match self {
+            Expr::Number(text) if text == "i" || text.starts_with('[') => true,
+            Expr::Number(text) => !text.starts_with('-') && !text.contains('/') && !text.ends_with('i'),
+            Expr::Variable(_) | Expr::Constant(_) => true,
+            Expr::Operation(_, _) => false,
+        }
+    }
+
+    fn infix_precedence(&self, operators: &Operators) -> u8 {
+        match self {
+            Expr::Number(text) if text.starts_with('[') => ATOM,
+            // "3+4i" is a sum and "4i" a product
+            Expr::Number(text) if is_complex_sum(text) => SUM,
+            Expr::Number(text) if text.starts_with('-') => NEGATION,
+            Expr::Number(text) if text.contains('/') => PRODUCT,
+            Expr::Number(text) if text.ends_with('i') && text != "i" => PRODUCT,
+            Expr::Operation(token, _) => match operators.written(token) {
+                Some(operator) => template_precedence(&operator.infix, operator.precedence),
+                // '3 m' is a product of a number and a unit
+                None if token.starts_with("->") || token.starts_with('@') => CONVERSION,
+                None if unit_operation(token).is_some() => PRODUCT,
+                None => ATOM,
+            },
+            _ => ATOM,
+        }
+    }
+
+    // Infix form with the built-in operators
+    pub fn to_infix(&self) -> String {
+        self.to_infix_with(Operators::builtin())
+    }
+
+    pub fn to_infix_with(&self, operators: &Operators) -> String {
+        let infix = |expr: &Expr| expr.to_infix_with(operators);
+        match self {
+            Expr::Number(text) if text.starts_with('[') => text.replace(',', ", "),
+            Expr::Number(text) | Expr::Variable(text) | Expr::Constant(text) => text.clone(),
+            Expr::Operation(token, operands) => match operators.written(token) {
+                Some(operator) => fill(&operator.infix, operator, operands, false, operators),
+                None if token.starts_with("->") => format!("{} to {}", infix(&operands[0]), &token[2..]),
+                None if token.starts_with('@') => format!("{} at {}", infix(&operands[0]), &token[1..]),
+                None if unit_operation(token).is_some() => {
+                    let operand = &operands[0];
+                    let parens = operand.infix_precedence(operators) < PRODUCT;
+                    format!("{} {}", wrap_infix(operand, parens, operators), token)
+                }
+                // Operators of a config file that is not loaded anymore, e.g. in an old session
+                None => {
+                    let arguments: Vec<String> = operands.iter().map(infix).collect();
+                    format!("{}({})", token, arguments.join(", "))
+                }
+            },
+        }
+    }
+
+    // Fractions are typeset as \frac and group themselves, so they count as atoms in LaTeX
+    fn latex_precedence(&self, operators: &Operators) -> u8 {
+        match self {
+            Expr::Number(text) if text.contains('/') => ATOM,
+            Expr::Operation(token, _) => match operators.written(token) {
+                Some(operator) => template_precedence(&operator.latex, operator.precedence),
+                None => self.infix_precedence(operators),
+            },
+            _ => self.infix_precedence(operators),
+        }
+    }
+
+    // LaTeX form with the built-in operators
+    pub fn to_latex(&self) -> String {
+        self.to_latex_with(Operators::builtin())
+    }
+
+    pub fn to_latex_with(&self, operators: &Operators) -> String {
+        match self {
+            Expr::Number(text) if text.starts_with('[') => pmatrix(text),
+            // Integers in another base keep their prefix, typeset like code
+            Expr::Number(text) if parse_integer_literal(text).is_some() => match text.strip_prefix('-') {
+                Some(literal) => format!(r"-\texttt{{{}}}", literal),
+                None => format!(r"\texttt{{{}}}", text),
+            },
+            Expr::Number(text) if is_complex_sum(text) => {
+                let (real, imaginary) = split_parts(text).unwrap();
+                format!("{} {} {}i", real, &imaginary[..1], &imaginary[1..])
+            }
+            Expr::Number(text) => match text.split_once('/') {
+                Some((numerator, denominator)) => format!(r"\frac{{{}}}{{{}}}", numerator, denominator),
+                None => text.clone(),
+            },
+            Expr::Variable(name) if name.chars().count() == 1 => name.clone(),
+            Expr::Variable(name) => format!(r"\mathrm{{{}}}", name),
+            Expr::Constant(name) if name == "pi" => r"\pi".to_owned(),
+            Expr::Constant(name) => name.clone(),
+            Expr::Operation(token, operands) => self.operation_to_latex(token, operands, operators),
+        }
+    }
+
+    fn operation_to_latex(&self, token: &str, operands: &[Expr], operators: &Operators) -> String {
+        match operators.written(token) {
+            Some(operator) => fill(&operator.latex, operator, operands, true, operators),
+            // Evaluation at a point, "x^{2} at 3" is written with an evaluation bar
+            None if token.starts_with('@') => {
+                let point = Expr::Number(token[1..].to_owned()).to_latex();
+                format!(r"\left. {} \right|_{{x = {}}}", operands[0].to_latex_with(operators), point)
+            }
+            None if unit_operation(token).is_some() => {
+                let (conversion, unit) = unit_operation(token).unwrap();
+                if conversion {
+                    format!(r"{} \rightarrow {}", operands[0].to_latex_with(operators), unit.to_latex())
+                } else {
+                    let parens = operands[0].latex_precedence(operators) < PRODUCT;
+                    format!(r"{}\,{}", wrap_latex(&operands[0], parens, operators), unit.to_latex())
+                }
+            }
+            None => {
+                let arguments: Vec<String> = operands.iter().map(|operand| operand.to_latex_with(operators)).collect();
+                format!(r"\operatorname{{{}}}\left({}\right)", token, arguments.join(", "))
+            }
+        }
+    }
+}
+
+// Writes the operands into the template of the operator. An operand at the start or the end gets
+// parentheses if it binds weaker, or as weak on the side the operator does not associate to
+fn fill(template: &str, operator: &Operator, operands: &[Expr], latex: bool, operators: &Operators) -> String {
+    let precedence = |expr: &Expr| {
+        if latex { expr.latex_precedence(operators) } else { expr.infix_precedence(operators) }
+    };
+    let render = |expr: &Expr, parens: bool| {
+        if latex { wrap_latex(expr, parens, operators) } else { wrap_infix(expr, parens, operators) }
+    };
+    let own = template_precedence(template, operator.precedence);
+    let pieces = operators::pieces(template);
+    let last = pieces.len() - 1;
+    let mut text = String::new();
+    for (position, piece) in pieces.iter().enumerate() {
+        match *piece {
+            Piece::Text(part) => text.push_str(part),
+            Piece::Operand(index, leaf_only) => {
+                let operand = &operands[index];
+                let parens = if leaf_only {
+                    !operand.is_leaf()
+                } else {
+                    let weaker = precedence(operand) < own;
+                    let equal = precedence(operand) == own;
+                    (position == 0 && (weaker || (operator.right_associative && equal)))
+                        || (position == last && position > 0 && (weaker || (!operator.right_associative && equal)))
+                };
+                // "\sin x", a leaf must not run into the name before it
+                if leaf_only && !parens && text.ends_with(|c: char| c.is_alphabetic() || c == '}') {
+                    text.push(' ');
+                }
+                text.push_str(&render(operand, parens));
+            }
+            Piece::AllOperands => {
+                let all: Vec<String> = operands.iter().map(|operand| render(operand, false)).collect();
+                text.push_str(&all.join(", "));
+            }
+        }
+    }
+    text
+}
+
+// Unit words like "km/h" and conversions like "->km/h", true for conversions
+fn unit_operation(token: &str) -> Option<(bool, Unit)> {
+    match token.strip_prefix("->") {
+        Some(unit) => Unit::parse_target(unit).map(|unit| (true, unit)),
+        None => Unit::parse(token).map(|unit| (false, unit)),
+    }
+}
+
+// Complex literals with a real and an imaginary part
+fn is_complex_sum(text: &str) -> bool {
+    matches!(split_parts(text), Some((real, _)) if !real.is_empty())
+}
+
+// Vectors are typeset as a column, matrices row by row
+fn pmatrix(text: &str) -> String {
+    let element = |element: &str| Some(Expr::Number(element.to_owned()).to_latex());
+    let body = match parse_array(text, element) {
+        Some(Array::Vector(elements)) => elements.join(r" \\ "),
+        Some(Array::Matrix(rows)) => {
+            let rows: Vec<String> = rows.iter().map(|row| row.join(" & ")).collect();
+            rows.join(r" \\ ")
+        }
+        None => return text.to_owned(),
+    };
+    format!(r"\begin{{pmatrix}} {} \end{{pmatrix}}", body)
+}
+
+fn wrap_infix(expr: &Expr, parens: bool, operators: &Operators) -> String {
+    let infix = expr.to_infix_with(operators);
+    if parens { format!("({})", infix) } else { infix }
+}
+
+fn wrap_latex(expr: &Expr, parens: bool, operators: &Operators) -> String {
+    let latex = expr.to_latex_with(operators);
+    if parens { format!(r"\left({}\right)", latex) } else { latex }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> Expr {
+        Expr::Number(text.to_owned())
+    }
+
+    fn op(token: &str, operands: Vec<Expr>) -> Expr {
+        Expr::operation(token, operands)
+    }
+
+    #[test]
+    fn test_infix_precedence() {
+        let sum = op("+", vec![number("5"), number("3")]);
+        assert_eq!(op("*", vec![sum.clone(), number("2")]).to_infix(), "(5 + 3) * 2");
+        assert_eq!(op("+", vec![op("*", vec![number("5"), number("3")]), number("2")]).to_infix(), "5 * 3 + 2");
+        assert_eq!(op("-", vec![number("1"), sum.clone()]).to_infix(), "1 - (5 + 3)");
+        assert_eq!(op("-", vec![sum, number("1")]).to_infix(), "5 + 3 - 1");
+        let quotient = op("/", vec![number("8"), number("4")]);
+        assert_eq!(op("/", vec![number("1"), quotient.clone()]).to_infix(), "1 / (8 / 4)");
+        assert_eq!(op("/", vec![quotient, number("2")]).to_infix(), "8 / 4 / 2");
+    }
+
+    #[test]
+    fn test_infix_power_and_negation() {
+        let power = op("^", vec![number("2"), number("3")]);
+        assert_eq!(op("^", vec![number("2"), power.clone()]).to_infix(), "2 ^ 2 ^ 3");
+        assert_eq!(op("^", vec![power.clone(), number("2")]).to_infix(), "(2 ^ 3) ^ 2");
+        assert_eq!(op("^", vec![number("-3"), number("2")]).to_infix(), "(-3) ^ 2");
+        assert_eq!(op("-", vec![number("5"), number("-3")]).to_infix(), "5 - -3");
+        assert_eq!(op("!", vec![op("+", vec![number("2"), number("1")])]).to_infix(), "(2 + 1)!");
+        assert_eq!(op("sqrt", vec![op("^", vec![number("1/4"), number("2")])]).to_infix(), "sqrt((1/4) ^ 2)");
+        assert_eq!(op("max", vec![number("1"), power]).to_infix(), "max(1, 2 ^ 3)");
+    }
+
+    #[test]
+    fn test_latex() {
+        let sum = op("+", vec![number("5"), number("3")]);
+        assert_eq!(op("*", vec![sum.clone(), number("2")]).to_latex(), r"\left(5 + 3\right) \cdot 2");
+        assert_eq!(op("/", vec![sum.clone(), number("2")]).to_latex(), r"\frac{5 + 3}{2}");
+        assert_eq!(op("^", vec![sum.clone(), sum.clone()]).to_latex(), r"\left(5 + 3\right)^{5 + 3}");
+        assert_eq!(op("-", vec![number("1"), sum]).to_latex(), r"1 - \left(5 + 3\right)");
+        let half = op("/", vec![Expr::Constant("pi".to_owned()), number("2")]);
+        assert_eq!(op("sin", vec![half.clone()]).to_latex(), r"\sin\left(\frac{\pi}{2}\right)");
+        assert_eq!(op("*", vec![half, number("1/3")]).to_latex(), r"\frac{\pi}{2} \cdot \frac{1}{3}");
+        assert_eq!(op("ln", vec![Expr::Variable("rate".to_owned())]).to_latex(), r"\ln \mathrm{rate}");
+    }
+
+    #[test]
+    fn test_complex_numbers() {
+        let sum = number("3-4i");
+        assert_eq!(op("*", vec![number("2"), sum.clone()]).to_infix(), "2 * (3-4i)");
+        assert_eq!(op("-", vec![number("2"), sum.clone()]).to_infix(), "2 - (3-4i)");
+        assert_eq!(op("^", vec![number("4i"), number("i")]).to_infix(), "(4i) ^ i");
+        assert_eq!(op("*", vec![number("2"), sum.clone()]).to_latex(), r"2 \cdot \left(3 - 4i\right)");
+        assert_eq!(op("conj", vec![sum.clone()]).to_latex(), r"\overline{3 - 4i}");
+        assert_eq!(op("re", vec![sum]).to_latex(), r"\operatorname{Re}\left(3 - 4i\right)");
+        let complex = op("cplx", vec![number("1"), op("+", vec![number("2"), number("3")])]);
+        assert_eq!(complex.to_infix(), "cplx(1, 2 + 3)");
+        assert_eq!(op("*", vec![complex, number("2")]).to_latex(), r"\left(1 + \left(2 + 3\right)i\right) \cdot 2");
+    }
+
+    #[test]
+    fn test_rpn_round_trip() {
+        let sum = op("+", vec![Expr::Variable("a".to_owned()), number("-3")]);
+        let expr = op("atan2", vec![op("!", vec![number("4")]), op("sqrt", vec![sum])]);
+        let tokens = expr.to_rpn();
+        assert_eq!(tokens.join(" "), "4 ! $a -3 + sqrt atan2");
+        let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        assert_eq!(Expr::from_rpn(&tokens), Some(expr));
+        assert_eq!(Expr::from_rpn(&["pi"]), Some(Expr::Constant("pi".to_owned())));
+        assert_eq!(Expr::from_rpn(&["1", "+"]), None);
+        assert_eq!(Expr::from_rpn(&["1", "2"]), None);
+        assert_eq!(Expr::from_rpn(&[]), None);
+        // A statistic takes everything before it
+        let tokens = ["1", "2", "+", "4", "mean"];
+        let mean = op("mean", vec![op("+", vec![number("1"), number("2")]), number("4")]);
+        assert_eq!(Expr::from_rpn(&tokens), Some(mean));
+        // 'count' of an empty stack
+        assert_eq!(Expr::from_rpn(&["count"]), Some(op("count", Vec::new())));
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let x = Expr::Constant("x".to_owned());
+        let square = op("^", vec![x.clone(), number("2")]);
+        assert_eq!(Expr::from_rpn(&["x", "2", "^"]), Some(square.clone()));
+        assert_eq!(op("diff", vec![square.clone()]).to_latex(), r"\frac{d}{dx}\left(x^{2}\right)");
+        assert_eq!(op("diff", vec![x]).to_latex(), r"\frac{d}{dx} x");
+        let point = op("@-1/2", vec![op("+", vec![square.clone(), number("1")])]);
+        assert_eq!(point.to_infix(), "x ^ 2 + 1 at -1/2");
+        assert_eq!(point.to_latex(), r"\left. x^{2} + 1 \right|_{x = \frac{-1}{2}}");
+        assert_eq!(op("*", vec![point, number("2")]).to_infix(), "(x ^ 2 + 1 at -1/2) * 2");
+        assert_eq!(Expr::from_rpn(&["x", "@3"]).unwrap().to_infix(), "x at 3");
+        assert_eq!(op("simp", vec![square]).to_infix(), "simp(x ^ 2)");
+    }
+
+    #[test]
+    fn test_statistics() {
+        let stddev = op("stddev", vec![number("1"), op("^", vec![number("2"), number("3")]), number("5")]);
+        assert_eq!(stddev.to_infix(), "stddev(1, 2 ^ 3, 5)");
+        assert_eq!(stddev.to_latex(), r"\sigma\left(1, 2^{3}, 5\right)");
+        let mean = op("mean", vec![number("1/2"), Expr::Variable("x".to_owned())]);
+        assert_eq!(mean.to_latex(), r"\bar{x}\left(\frac{1}{2}, x\right)");
+        assert_eq!(op("smax", vec![number("1"), number("2")]).to_latex(), r"\max\left(1, 2\right)");
+        assert_eq!(op("slope", vec![number("1"), number("2")]).to_infix(), "slope(1, 2)");
+    }
+}
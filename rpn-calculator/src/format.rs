@@ -0,0 +1,211 @@
+// Display settings for printing values: integers in another base and a fixed number of digits.
+// They only change how values are printed, the values on the stack keep their full precision
+use crate::bigint::BigInt;
+use crate::matrix;
+use crate::value::Value;
+
+// Base for printing integers, numbers with a fractional part are always printed in decimal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+// Number of digits after the decimal point, for 'sci' and 'eng' the digits of the mantissa
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberFormat {
+    Standard,
+    Fixed(usize),
+    Scientific(usize),
+    Engineering(usize),
+}
+
+// Largest number of digits for 'fix', 'sci' and 'eng'
+pub(crate) const MAX_DIGITS: usize = 20;
+
+impl Radix {
+    // Also the command that selects the base
+    pub fn name(self) -> &'static str {
+        match self {
+            Radix::Decimal => "dec",
+            Radix::Hexadecimal => "hex",
+            Radix::Octal => "oct",
+            Radix::Binary => "bin",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Radix> {
+        match name {
+            "dec" => Some(Radix::Decimal),
+            "hex" => Some(Radix::Hexadecimal),
+            "oct" => Some(Radix::Octal),
+            "bin" => Some(Radix::Binary),
+            _ => None,
+        }
+    }
+
+    fn base_and_prefix(self) -> (u32, &'static str) {
+        match self {
+            Radix::Decimal => (10, ""),
+            Radix::Hexadecimal => (16, "0x"),
+            Radix::Octal => (8, "0o"),
+            Radix::Binary => (2, "0b"),
+        }
+    }
+}
+
+impl NumberFormat {
+    // 'fix 4' is Fixed(4), 'std' is the standard format
+    pub fn from_command(command: &str, digits: usize) -> Option<NumberFormat> {
+        match command {
+            "std" => Some(NumberFormat::Standard),
+            "fix" => Some(NumberFormat::Fixed(digits)),
+            "sci" => Some(NumberFormat::Scientific(digits)),
+            "eng" => Some(NumberFormat::Engineering(digits)),
+            _ => None,
+        }
+    }
+
+    // The command that selects the format, e.g. "fix 4"
+    pub fn command(self) -> String {
+        match self {
+            NumberFormat::Standard => "std".to_owned(),
+            NumberFormat::Fixed(digits) => format!("fix {}", digits),
+            NumberFormat::Scientific(digits) => format!("sci {}", digits),
+            NumberFormat::Engineering(digits) => format!("eng {}", digits),
+        }
+    }
+
+    pub fn digits(self) -> usize {
+        match self {
+            NumberFormat::Standard => 0,
+            NumberFormat::Fixed(digits) | NumberFormat::Scientific(digits) | NumberFormat::Engineering(digits) => {
+                digits
+            }
+        }
+    }
+}
+
+// Integer literals in another base: "0xff", "0o17" and "0b1010", optionally with a leading '-'
+pub(crate) fn parse_integer_literal(text: &str) -> Option<BigInt> {
+    let (negative, literal) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let radix = match literal.get(..2)? {
+        "0x" => 16,
+        "0o" => 8,
+        "0b" => 2,
+        _ => return None,
+    };
+    let magnitude = BigInt::parse_radix(&literal[2..], radix)?;
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+// A value as it is printed with the given settings, vectors and matrices element by element
+pub fn format_value(value: &Value, radix: Radix, format: NumberFormat) -> String {
+    match value {
+        Value::Vector(_) | Value::Matrix(_) => {
+            let array = matrix::from_value(value).unwrap();
+            matrix::format_array(&array.map(|element| format_value(element, radix, format)), " ")
+        }
+        Value::Complex(z) if format != NumberFormat::Standard => {
+            let sign = if z.im.is_sign_negative() { '-' } else { '+' };
+            format!("{}{}{}i", format_real(z.re, format), sign, format_real(z.im.abs(), format))
+        }
+        Value::Complex(_) | Value::Symbolic(_) => value.to_string(),
+        Value::Quantity(magnitude, unit) => format!("{} {}", format_value(magnitude, radix, format), unit),
+        _ => match integer_value(value) {
+            Some(integer) if radix != Radix::Decimal => {
+                let (base, prefix) = radix.base_and_prefix();
+                let sign = if integer.is_negative() { "-" } else { "" };
+                format!("{}{}{}", sign, prefix, integer.abs().to_string_radix(base))
+            }
+            _ if format == NumberFormat::Standard => value.to_string(),
+            _ => format_real(value.to_f64(), format),
+        },
+    }
+}
+
+fn integer_value(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Exact(x) if x.is_integer() => Some(x.numerator().clone()),
+        Value::Float(a) => BigInt::from_f64(*a),
+        _ => None,
+    }
+}
+
+fn format_real(x: f64, format: NumberFormat) -> String {
+    match format {
+        NumberFormat::Standard => x.to_string(),
+        NumberFormat::Fixed(digits) => format!("{:.*}", digits, x),
+        NumberFormat::Scientific(digits) => format!("{:.*e}", digits, x),
+        NumberFormat::Engineering(digits) => engineering(x, digits),
+    }
+}
+
+// Scientific notation with an exponent that is a multiple of three, like "12.50e3"
+fn engineering(x: f64, digits: usize) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return format!("{:.*}e0", digits, x);
+    }
+    let mut exponent = (x.abs().log10().floor() as i32).div_euclid(3) * 3;
+    let mut mantissa = x / 10f64.powi(exponent);
+    // Rounding the mantissa can carry it over to 1000
+    if format!("{:.*}", digits, mantissa.abs()).parse::<f64>().unwrap() >= 1000.0 {
+        exponent += 3;
+        mantissa /= 1000.0;
+    }
+    format!("{:.*}e{}", digits, mantissa, exponent)
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex::Complex;
+    use crate::rational::Rational;
+
+    fn exact(text: &str) -> Value {
+        Value::Exact(Rational::parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        assert_eq!(parse_integer_literal("0xff"), BigInt::parse("255"));
+        assert_eq!(parse_integer_literal("-0o17"), BigInt::parse("-15"));
+        assert_eq!(parse_integer_literal("0b1010"), BigInt::parse("10"));
+        assert_eq!(parse_integer_literal("0b102"), None);
+        assert_eq!(parse_integer_literal("0x"), None);
+        assert_eq!(parse_integer_literal("255"), None);
+    }
+
+    #[test]
+    fn test_radix() {
+        assert_eq!(format_value(&exact("255"), Radix::Hexadecimal, NumberFormat::Standard), "0xff");
+        assert_eq!(format_value(&Value::from(-10.0), Radix::Binary, NumberFormat::Standard), "-0b1010");
+        assert_eq!(format_value(&exact("8"), Radix::Octal, NumberFormat::Fixed(2)), "0o10");
+        // Fractions stay decimal
+        assert_eq!(format_value(&exact("1/4"), Radix::Hexadecimal, NumberFormat::Standard), "1/4");
+        assert_eq!(format_value(&Value::from(2.5), Radix::Hexadecimal, NumberFormat::Fixed(1)), "2.5");
+    }
+
+    #[test]
+    fn test_number_formats() {
+        let value = Value::from(12345.678);
+        assert_eq!(format_value(&value, Radix::Decimal, NumberFormat::Fixed(2)), "12345.68");
+        assert_eq!(format_value(&value, Radix::Decimal, NumberFormat::Scientific(3)), "1.235e4");
+        assert_eq!(format_value(&value, Radix::Decimal, NumberFormat::Engineering(2)), "12.35e3");
+        assert_eq!(format_value(&Value::from(0.00012), Radix::Decimal, NumberFormat::Engineering(1)), "120.0e-6");
+        assert_eq!(format_value(&Value::from(999.96), Radix::Decimal, NumberFormat::Engineering(1)), "1.0e3");
+        assert_eq!(format_value(&Value::from(0.0), Radix::Decimal, NumberFormat::Engineering(1)), "0.0e0");
+        assert_eq!(format_value(&exact("1/3"), Radix::Decimal, NumberFormat::Fixed(3)), "0.333");
+        let complex = Value::from(Complex::new(1.0, -0.5));
+        assert_eq!(format_value(&complex, Radix::Decimal, NumberFormat::Fixed(2)), "1.00-0.50i");
+        let vector = Value::Vector(vec![exact("1/2"), Value::from(2.0)]);
+        assert_eq!(format_value(&vector, Radix::Decimal, NumberFormat::Fixed(1)), "[0.5 2.0]");
+    }
+}
@@ -0,0 +1,270 @@
+// Minimal JSON values for the evaluation service, just enough to read requests and write responses
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Arrays and objects nest at most this deep. Valid requests only need two levels, the rest is headroom
+// for clients that send extra members; far deeper values are rejected before the recursion can exhaust the stack
+const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Members keep their order, so responses always list their fields the same way
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // The whole text has to be a single value, None for anything else
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        if chars.next().is_none() { Some(value) } else { None }
+    }
+
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(members.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }
+
+    // Member of an object, None for missing keys and other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(text: &str) -> Self {
+        Json::String(text.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(text: String) -> Self {
+        Json::String(text)
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+// Depth counts the arrays and objects around the value
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Option<Json> {
+    skip_whitespace(chars);
+    match *chars.peek()? {
+        '{' | '[' if depth == MAX_DEPTH => None,
+        '{' => parse_object(chars, depth + 1),
+        '[' => parse_array(chars, depth + 1),
+        '"' => parse_string(chars).map(Json::String),
+        'n' => parse_keyword(chars, "null", Json::Null),
+        't' => parse_keyword(chars, "true", Json::Bool(true)),
+        'f' => parse_keyword(chars, "false", Json::Bool(false)),
+        _ => parse_number(chars),
+    }
+}
+
+fn parse_keyword(chars: &mut Peekable<Chars>, keyword: &str, value: Json) -> Option<Json> {
+    keyword.chars().all(|expected| chars.next() == Some(expected)).then_some(value)
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Option<Json> {
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+        text.push(c);
+    }
+    text.parse().ok().filter(|number: &f64| number.is_finite()).map(Json::Number)
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    chars.next();
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => text.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'u' => parse_unicode_escape(chars)?,
+                c @ ('"' | '\\' | '/') => c,
+                _ => return None,
+            }),
+            c if c.is_control() => return None,
+            c => text.push(c),
+        }
+    }
+}
+
+// "\u00e9", characters outside the basic plane are written as a surrogate pair
+fn parse_unicode_escape(chars: &mut Peekable<Chars>) -> Option<char> {
+    let hex = |chars: &mut Peekable<Chars>| {
+        let digits: String = chars.by_ref().take(4).collect();
+        if digits.len() == 4 { u32::from_str_radix(&digits, 16).ok() } else { None }
+    };
+    let high = hex(chars)?;
+    if !(0xd800..0xdc00).contains(&high) {
+        return char::from_u32(high);
+    }
+    if chars.next()? != '\\' || chars.next()? != 'u' {
+        return None;
+    }
+    let low = hex(chars)?;
+    if !(0xdc00..0xe000).contains(&low) {
+        return None;
+    }
+    char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+}
+
+fn parse_array(chars: &mut Peekable<Chars>, depth: usize) -> Option<Json> {
+    chars.next();
+    let mut elements = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Some(Json::Array(elements));
+    }
+    loop {
+        elements.push(parse_value(chars, depth)?);
+        skip_whitespace(chars);
+        match chars.next()? {
+            ',' => continue,
+            ']' => return Some(Json::Array(elements)),
+            _ => return None,
+        }
+    }
+}
+
+fn parse_object(chars: &mut Peekable<Chars>, depth: usize) -> Option<Json> {
+    chars.next();
+    let mut members = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Some(Json::Object(members));
+    }
+    loop {
+        skip_whitespace(chars);
+        if chars.peek() != Some(&'"') {
+            return None;
+        }
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        members.push((key, parse_value(chars, depth)?));
+        skip_whitespace(chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => return Some(Json::Object(members)),
+            _ => return None,
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+// Compact form without any whitespace
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => write_string(f, text),
+            Json::Array(elements) => {
+                f.write_str("[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = Json::parse(r#" {"rpn": "5 3 +", "tokens": ["1", "2.5"], "n": -1.5e2, "ok": [true, null]} "#);
+        let json = json.unwrap();
+        assert_eq!(json.get("rpn"), Some(&Json::from("5 3 +")));
+        assert_eq!(json.get("tokens"), Some(&Json::Array(vec![Json::from("1"), Json::from("2.5")])));
+        assert_eq!(json.get("n"), Some(&Json::Number(-150.0)));
+        assert_eq!(json.get("ok"), Some(&Json::Array(vec![Json::Bool(true), Json::Null])));
+        assert_eq!(json.get("missing"), None);
+        assert_eq!(Json::parse(r#""a\"b\\c\u00e9\ud83d\ude00\n""#), Some(Json::from("a\"b\\c\u{e9}\u{1f600}\n")));
+        assert_eq!(Json::parse("{}"), Some(Json::Object(Vec::new())));
+        assert_eq!(Json::parse("[]"), Some(Json::Array(Vec::new())));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for text in ["", "{", r#"{"a" 1}"#, r#"{"a": 1,}"#, "[1 2]", "nul", r#""\x""#, "1 2", "{1: 2}", "1e999"] {
+            assert_eq!(Json::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(Json::parse(&nested(MAX_DEPTH + 1)), None);
+        assert_eq!(Json::parse(&"[".repeat(200_000)), None);
+        assert_eq!(Json::parse(&r#"{"a":"#.repeat(MAX_DEPTH + 1)), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let json = Json::object(vec![
+            ("result", Json::from("1/2")),
+            ("stack", Json::Array(vec![Json::from("a\"b"), Json::Null])),
+            ("count", Json::Number(2.0)),
+            ("text", Json::from("\\frac{1}{2}\n\u{1}")),
+        ]);
+        let text = json.to_string();
+        assert_eq!(text, r#"{"result":"1/2","stack":["a\"b",null],"count":2,"text":"\\frac{1}{2}\n\u0001"}"#);
+        assert_eq!(Json::parse(&text), Some(json));
+    }
+}
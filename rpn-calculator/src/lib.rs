@@ -0,0 +1,29 @@
+// RPN calculator library, the binary in main.rs is only a thin interactive front end
+mod bigint;
+mod calculator;
+mod complex;
+mod error;
+mod expression;
+mod format;
+mod matrix;
+mod operators;
+mod parser;
+mod plot;
+mod rational;
+mod statistics;
+mod symbolic;
+mod units;
+mod value;
+
+pub use bigint::BigInt;
+pub use calculator::{AngleMode, NumberMode, RPNCalculator, StackEntry};
+pub use complex::Complex;
+pub use error::CalcError;
+pub use expression::Expr;
+pub use format::{NumberFormat, Radix};
+pub use operators::Operators;
+pub use parser::infix_to_rpn;
+pub use plot::Plot;
+pub use rational::Rational;
+pub use units::Unit;
+pub use value::Value;
@@ -0,0 +1,95 @@
+// Line editor support for the interactive mode: tab completion and the stack prompt
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use rpn_calculator::RPNCalculator;
+
+// Commands of the interactive session that are not calculator words
+const SESSION_COMMANDS: &[&str] = &["exit", "vars", "words", "show", "save", "load", "infix", "plot"];
+
+// Number of stack entries shown in the prompt
+const PROMPT_ENTRIES: usize = 3;
+
+// Completes the word in front of the cursor, the words are refreshed after every command
+#[derive(Default)]
+pub struct CalcHelper {
+    words: Vec<String>,
+}
+
+impl CalcHelper {
+    pub fn refresh(&mut self, calc: &RPNCalculator) {
+        self.words = calc.completions();
+        self.words.extend(SESSION_COMMANDS.iter().map(|command| command.to_string()));
+        self.words.sort();
+        self.words.dedup();
+    }
+
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        self.words.iter().filter(|word| word.starts_with(prefix)).cloned().collect()
+    }
+}
+
+impl Completer for CalcHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(char::is_whitespace).map_or(0, |index| index + 1);
+        Ok((start, self.candidates(&line[start..pos])))
+    }
+}
+
+impl Hinter for CalcHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CalcHelper {}
+
+impl Validator for CalcHelper {}
+
+impl Helper for CalcHelper {}
+
+// The top few stack entries followed by '>', e.g. "... 3 4 5 > "
+pub fn prompt(calc: &RPNCalculator) -> String {
+    let stack = calc.stack();
+    let shown = &stack[stack.len().saturating_sub(PROMPT_ENTRIES)..];
+    let mut parts: Vec<String> = shown.iter().map(|value| calc.format_value(value)).collect();
+    if stack.len() > PROMPT_ENTRIES {
+        parts.insert(0, "...".to_owned());
+    }
+    parts.push("> ".to_owned());
+    parts.join(" ")
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let mut calc = RPNCalculator::new();
+        calc.evaluate_line(": square dup * ; 2 =seconds").unwrap();
+        let mut helper = CalcHelper::default();
+        helper.refresh(&calc);
+        assert_eq!(helper.candidates("sq"), ["sqrt", "square"]);
+        assert_eq!(helper.candidates("sh"), ["shl", "show", "shr"]);
+        assert_eq!(helper.candidates("$"), ["$seconds"]);
+        assert_eq!(helper.candidates("as"), ["asin"]);
+    }
+
+    #[test]
+    fn test_prompt() {
+        let mut calc = RPNCalculator::new();
+        assert_eq!(prompt(&calc), "> ");
+        calc.evaluate_line("1 2").unwrap();
+        assert_eq!(prompt(&calc), "1 2 > ");
+        calc.evaluate_line("3 4.5").unwrap();
+        assert_eq!(prompt(&calc), "... 2 3 4.5 > ");
+        calc.evaluate_line("hex fix 2").unwrap();
+        assert_eq!(prompt(&calc), "... 0x2 0x3 4.50 > ");
+    }
+}
@@ -1,456 +1,456 @@
 // Library for handling user inputs
-use std::io;
-
-// Class declaration, clone trait
-#[derive(Clone)]
-struct RPNCalculator {
-    stack: Vec<f64>,
-    history_stack: Vec<String>,
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::net::TcpListener;
+// Libraries for reading the command line arguments, script, session and history files and setting the exit code
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+
+// Line editor with history navigation and tab completion for the interactive mode
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+
+use rpn_calculator::{Operators, RPNCalculator, Unit};
+
+mod json;
+mod line_editor;
+mod server;
+
+use line_editor::CalcHelper;
+
+fn welcome_prompt() {
+    println!("-----------------------------");
+    println!("Welcome to the RPN calculator, please input your equation separated by spaces or 'enter'!");
+    println!("Type 'undo'/'redo' to take back or reapply an entry, 'show' to see every stack entry, 'exit' to quit.");
+    println!("Store values with 'sto name' or '=name', recall them with 'rcl name' or '$name', list them with 'vars'.");
+    println!("Type 'exact' for exact integers and fractions, 'float' to go back to floating point.");
+    println!("Type 'deg' or 'rad' to select the angle unit of sin, cos, tan and their inverses.");
+    println!("Enter complex numbers as '3+4i' or with 'cplx', take them apart with 're', 'im', 'arg' and 'conj'.");
+    println!("Enter vectors as '[1 2 3]' and matrices as '[[1 2] [3 4]]'.");
+    println!("Use 'dot', 'cross', 'det', 'inv', 'transpose' and 'norm' for linear algebra.");
+    println!("Enter integers as '0xff', '0o17' or '0b1010', use 'and', 'or', 'xor', 'not', 'shl' and 'shr' on them.");
+    println!("Print integers with 'hex', 'oct', 'bin' or 'dec', and choose 'fix N', 'sci N', 'eng N' or 'std' digits.");
+    println!("Give numbers a unit like '3 m' or '10 km/h' and convert them with 'to m/s'.");
+    println!("Use 'mean', 'median', 'var', 'stddev', 'smin', 'smax' and 'count' on the whole stack.");
+    println!("Type 'linreg' on pairs 'x1 y1 x2 y2 ...' for the intercept and slope of the best fitting line.");
+    println!("Type 'x' for expressions of x, derive them with 'diff', simplify with 'simp', evaluate with 'at 3'.");
+    println!("Draw an expression of x with 'plot -2 2', or save it as an image with 'plot -2 2 chart.svg'.");
+    println!("Define new words with ': name body ;', list them with 'words'.");
+    println!("Add operators in '~/{}', a section '[name]' with 'arity = 2' and 'rpn = ...' each.", OPERATORS_FILE);
+    println!("Keep the whole session with 'save <file>' and restore it with 'load <file>'.");
+    println!("Type 'infix <expression>' to calculate an infix expression like '(5 + 3) * sqrt(16)'.");
+    println!("Recall earlier lines with the arrow keys and complete words with 'Tab'.");
+    println!("-----------------------------");
 }
 
-impl RPNCalculator {
-    // Constructor, initializing the vectors
-    fn new() -> Self {
-        Self {
-            stack: Vec::new(),
-            history_stack: Vec::new(),
-        }
-    }
+// Samples of 'plot', the size of the chart in the terminal in characters and of the SVG image in pixels
+const PLOT_SAMPLES: usize = 401;
+const PLOT_COLUMNS: usize = 60;
+const PLOT_ROWS: usize = 15;
+const SVG_WIDTH: u32 = 640;
+const SVG_HEIGHT: u32 = 400;
+
+// Every accepted input line is appended to this file in the home directory and replayed on the next start
+const HISTORY_FILE: &str = ".rpn_calculator_history";
+// Only the most recent lines are kept, so the file and the replay on start stay small
+const HISTORY_LINES: usize = 1000;
+
+// Further operators are read from this file in the home directory on every start, if it exists
+const OPERATORS_FILE: &str = ".rpn_calculator_operators";
+
+fn usage() -> ! {
+    eprintln!("Usage: rpn-calculator [--infix] [--repl | -f <script.rpn>]");
+    eprintln!("       rpn-calculator --serve <address>");
+    eprintln!("Without arguments the calculator runs interactively, or reads from stdin if it is piped.");
+    eprintln!("With '--infix' every line is an infix expression instead of RPN.");
+    eprintln!("With '--repl' piped input runs through the interactive session, like typed lines.");
+    eprintln!("With '--serve 127.0.0.1:8080' expressions are evaluated over HTTP, POST JSON to '/eval'.");
+    eprintln!("Operators defined in '~/{}' are available in every mode.", OPERATORS_FILE);
+    process::exit(2);
+}
 
-    // Applying operation to the stack
-    fn apply_operation(&mut self, token: &str) {
-        self.history_stack.push(token.to_owned());
-        match token {
-            "+" | "-" | "*" | "/" | "^" => {
-                self.arithmetical_operation_handling(token);
-            }
-            "sqrt" | "log" | "abs" => {
-                self.log_abs_sqrt_operation_handling(token);
-            }
-            "!" => {
-                self.factorial_operation_handling();
-            }
-            "++" => {
-                self.full_stack_addition_handling();
+// Main function for executing the RPN calculator
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // '--infix' can be combined with every mode
+    let infix = args.iter().position(|arg| arg == "--infix").map(|index| args.remove(index)).is_some();
+    let repl = args.iter().position(|arg| arg == "--repl").map(|index| args.remove(index)).is_some();
+    let calc = RPNCalculator::with_operators(load_operator_file());
+    match args.as_slice() {
+        [] if repl || io::stdin().is_terminal() => run_interactive(calc, infix),
+        _ if repl => usage(),
+        [] => run_batch_and_exit(calc, io::stdin().lock(), infix),
+        [flag, path] if flag == "-f" => match File::open(path) {
+            Ok(file) => run_batch_and_exit(calc, BufReader::new(file), infix),
+            Err(error) => {
+                eprintln!("Cannot open '{}': {}", path, error);
+                process::exit(2);
             }
-            "**" => {
-                self.full_stack_multiplication_handling();
+        },
+        [flag, address] if flag == "--serve" && !infix => match TcpListener::bind(address) {
+            Ok(listener) => {
+                println!("Serving on http://{}/eval", address);
+                server::serve(listener, calc)
             }
-            _ => {
-                match token.parse::<f64>() {
-                    Ok(_) => self.new_number_handling(token),
-                    Err(_) => {
-                        println!("Invalid input '{}'", token);
-                        self.history_stack.pop();
-                    }
-                }
+            Err(error) => {
+                eprintln!("Cannot listen on '{}': {}", address, error);
+                process::exit(2);
             }
-        }
-    }
-
-    fn arithmetical_operation_handling(&mut self, token: &str) {
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
-        let result = match token {
-            "+" => a + b,
-            "-" => a - b,
-            "*" => a * b,
-            "/" => a / b,
-            "^" => a.powf(b),
-            _ => unreachable!(),
-        };
-        self.stack.push(result);
-    }
-
-    fn log_abs_sqrt_operation_handling(&mut self, token: &str) {
-        let a: f64 = self.stack.pop().unwrap();
-        let result = match token {
-            "sqrt" => a.sqrt(),
-            "log" => a.log10(),
-            "abs" => a.abs(),
-            _ => unreachable!(),
-        };
-
-        self.stack.push(result);
+        },
+        _ => usage(),
     }
+}
 
-    fn factorial_operation_handling(&mut self) {
-        let a = self.stack.pop().unwrap();
-        if a.fract() == 0.0 {
-            let result = (1..=a as u64).product::<u64>() as f64;
-            self.stack.push(result);
-        } else {
-            println!("Invalid input '{}', cannot calculate the factorial", a);
-            self.history_stack.pop();
+// The built-in operators and those of the config file. A broken file stops the calculator, rather than
+// running without the operators it defines
+fn load_operator_file() -> Rc<Operators> {
+    let mut operators = Operators::default();
+    let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(OPERATORS_FILE));
+    if let Some(text) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+        if let Err(error) = operators.load(&text) {
+            eprintln!("Cannot load operators from '{}': {}", path.unwrap().display(), error);
+            process::exit(2);
         }
     }
+    Rc::new(operators)
+}
 
-    fn full_stack_addition_handling(&mut self) {
-        let result: f64 = self.stack.iter().sum();
-
-        self.stack.clear();
-        self.stack.push(result);
-    }
-
-    fn full_stack_multiplication_handling(&mut self) {
-        let result: f64 = self.stack.iter().product();
-
-        self.stack.clear();
-        self.stack.push(result);
-    }
-
-    fn new_number_handling(&mut self, token: &str) {
-        if let Ok(num) = token.parse::<f64>() {
-            self.stack.push(num);
+fn run_interactive(mut calc: RPNCalculator, infix: bool) {
+    welcome_prompt();
+    let config = Config::builder().completion_type(CompletionType::List).auto_add_history(true).build();
+    let mut editor: Editor<CalcHelper, DefaultHistory> = match Editor::with_config(config) {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("Cannot start the line editor: {}", error);
+            process::exit(1);
         }
-    }
-
-    fn welcome_prompt() {
-        println!("-----------------------------");
-        println!("Welcome to the RPN calculator, please input your equation with 'enter' between!");
-        println!("Type 'exit' to quit.");
-        println!("-----------------------------");
-    }
-
-    fn get_result(&self) -> Option<f64> {
-        if self.stack.len() == 1 { self.stack.last().cloned() } else { None }
-    }
-
-    fn reconstruct_expression_infix(&mut self) -> String {
-        if let Some(token) = self.history_stack.pop() {
-            match token.as_str() {
-                "+" | "-" | "*" | "/" | "^" => {
-                    let right = self.reconstruct_expression_infix();
-                    let left = self.reconstruct_expression_infix();
-                    format!("({} {} {})", left, token, right)
-                }
-                "++" => {
-                    let mut terms = Vec::new();
-                    while !self.history_stack.is_empty() {
-                        terms.push(self.reconstruct_expression_infix());
-                    }
-                    format!("({})", terms.join(" + "))
-                }
-                "**" => {
-                    let mut terms = Vec::new();
-                    while !self.history_stack.is_empty() {
-                        terms.push(self.reconstruct_expression_infix());
-                    }
-                    format!("({})", terms.join(" * "))
-                }
-                "!" => {
-                    let operand = self.reconstruct_expression_infix();
-                    format!("({}!)", operand)
-                }
-                "abs" => {
-                    let operand = self.reconstruct_expression_infix();
-                    format!("abs({})", operand)
-                }
-                "sqrt" => {
-                    let operand = self.reconstruct_expression_infix();
-                    format!("sqrt({})", operand)
-                }
-                "log" => {
-                    let operand = self.reconstruct_expression_infix();
-                    format!("log10({})", operand)
-                }
-                _ => token, // numbers
-            }
-        } else {
-            String::new() // empty stack
+    };
+    editor.set_helper(Some(CalcHelper::default()));
+
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history {
+        let restored = replay_history(&mut calc, path);
+        if restored > 0 {
+            println!("Restored {} line(s) from '{}'.", restored, path.display());
+            print_current_result(&calc);
         }
-    }
-
-    fn reconstruct_expression_latex(&mut self) -> String {
-        if let Some(token) = self.history_stack.pop() {
-            match token.as_str() {
-                "+" | "-" => {
-                    let right = self.reconstruct_expression_latex();
-                    let left = self.reconstruct_expression_latex();
-                    format!("{{{} {} {}}}", left, token, right)
-                }
-                "*" => {
-                    let right = self.reconstruct_expression_latex();
-                    let left = self.reconstruct_expression_latex();
-                    format!("{{{} \\cdot {}}}", left, right)
-                }
-                "/" => {
-                    let right = self.reconstruct_expression_latex();
-                    let left = self.reconstruct_expression_latex();
-                    format!("{{\\frac{{{}}}{{{}}}}}", left, right)
-                }
-                "^" => {
-                    let right = self.reconstruct_expression_latex();
-                    let left = self.reconstruct_expression_latex();
-                    format!("{{{}^{{{}}}}}", left, right)
-                }
-                "++" => {
-                    let mut terms = Vec::new();
-                    while !self.history_stack.is_empty() {
-                        terms.push(self.reconstruct_expression_latex());
-                    }
-                    format!("{{{}}}", terms.join(" + "))
-                }
-                "**" => {
-                    let mut terms = Vec::new();
-                    while !self.history_stack.is_empty() {
-                        terms.push(self.reconstruct_expression_latex());
-                    }
-                    format!("{{{}}}", terms.join(" \\cdot "))
-                }
-                "!" => {
-                    let operand = self.reconstruct_expression_latex();
-                    format!("{{{}}}!", operand)
-                }
-                "abs" => {
-                    let operand = self.reconstruct_expression_latex();
-                    format!(r"\left| {{{}}} \right|", operand)
-                }
-                "sqrt" => {
-                    let operand = self.reconstruct_expression_latex();
-                    format!(r"\sqrt{{{}}}", operand)
-                }
-                "log" => {
-                    let operand = self.reconstruct_expression_latex();
-                    format!(r"\log_{{10}} {{{}}}", operand)
-                }
-                _ => token, // numbers
-            }
-        } else {
-            String::new() // if the stack is empty
+        // The recorded lines can be recalled with the arrow keys as well
+        for line in fs::read_to_string(path).unwrap_or_default().lines() {
+            let _ = editor.add_history_entry(line);
         }
     }
-}
-
-// Main function for executing the RPN calculator
-fn main() {
-    let mut calc = RPNCalculator::new();
-    RPNCalculator::welcome_prompt();
-    let mut input = String::new();
 
     // "Main loop", repeating logic for each input
     loop {
-        input.clear();
-        io::stdin().read_line(&mut input).unwrap();
-        let input: &str = input.trim();
+        editor.helper_mut().unwrap().refresh(&calc);
+        let line = match editor.readline(&line_editor::prompt(&calc)) {
+            Ok(line) => Some(line),
+            // Ctrl-C only discards the current line
+            Err(ReadlineError::Interrupted) => continue,
+            // Stop on end of input just like on 'exit'
+            Err(ReadlineError::Eof) => None,
+            Err(error) => {
+                eprintln!("Cannot read input: {}", error);
+                None
+            }
+        };
+        let input: &str = line.as_deref().unwrap_or("exit").trim();
 
         if input.eq_ignore_ascii_case("exit") {
             println!("Exiting RPN Calculator...");
-            println!("Your infix calculation is: {}", calc.clone().reconstruct_expression_infix());
-            println!("Your LaTeX calculation is: {}", calc.clone().reconstruct_expression_latex());
+            println!("Your infix calculation is: {}", calc.infix());
+            println!("Your LaTeX calculation is: {}", calc.latex());
 
             match calc.get_result() {
-                Some(value) => println!("The final result is: {}", value),
+                Some(value) => println!("The final result is: {}", calc.format_value(&value)),
                 None => println!("No result available."),
             }
             break;
         }
 
-        calc.apply_operation(input);
+        if handle_session_command(&mut calc, input) {
+            continue;
+        }
 
-        match input {
-            "+" | "-" | "*" | "/" | "^" | "sqrt" | "log" | "abs" | "++" | "**" | "!" => {
-                if let Some(value) = calc.get_result() {
-                    println!("The current result is: {}", value);
-                }
+        // Infix input, either every line with '--infix' or after the 'infix' command
+        let expression = match input.strip_prefix("infix") {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => Some(rest.trim()),
+            _ if infix => Some(input),
+            _ => None,
+        };
+        if let Some(expression) = expression {
+            if evaluate_infix_input(&mut calc, expression) {
+                record_history(history.as_deref(), &format!("infix {}", expression));
             }
+            continue;
+        }
+
+        if let Err(error) = calc.evaluate_line(input) {
+            println!("{}", error);
+            continue;
+        }
+        record_history(history.as_deref(), input);
+
+        match input.split_whitespace().last() {
+            Some(word) if calc.is_operator(word) => print_current_result(&calc),
+            Some("undo" | "redo") => print_current_result(&calc),
+            Some(word) if calc.is_defined(word) => print_current_result(&calc),
+            // Units like '3 m' and conversions like 'to km/h'
+            Some(word) if Unit::parse(word).is_some() => print_current_result(&calc),
+            // Evaluations like 'at 3'
+            _ if input.split_whitespace().rev().nth(1) == Some("at") => print_current_result(&calc),
             _ => {}
         }
     }
 }
 
+fn print_current_result(calc: &RPNCalculator) {
+    if let Some(value) = calc.get_result() {
+        println!("The current result is: {}", calc.format_value(&value));
+    }
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// Commands that only make sense in the interactive session, returns false for normal input
+fn handle_session_command(calc: &mut RPNCalculator, input: &str) -> bool {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    match words.as_slice() {
+        ["show"] => print_entries(calc),
+        ["vars"] => print_variables(calc),
+        ["words"] => print_definitions(calc),
+        ["save", path] => match fs::write(path, calc.save_session()) {
+            Ok(()) => println!("Session saved to '{}'.", path),
+            Err(error) => println!("Cannot save '{}': {}", path, error),
+        },
+        ["load", path] => match fs::read_to_string(path) {
+            Ok(text) => match calc.load_session(&text) {
+                Ok(()) => println!("Session loaded from '{}'.", path),
+                Err(error) => println!("{}", error),
+            },
+            Err(error) => println!("Cannot open '{}': {}", path, error),
+        },
+        ["plot", xmin, xmax] => print_plot(calc, xmin, xmax, None),
+        ["plot", xmin, xmax, path] => print_plot(calc, xmin, xmax, Some(path)),
+        _ => return false,
+    }
+    true
+}
 
-    #[test]
-    fn test_plus() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("3");
-        calculator.apply_operation("+");
-        assert_eq!(calculator.get_result(), Some(8.0));
-    }
-    #[test]
-    fn test_minus() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("3");
-        calculator.apply_operation("-");
-        assert_eq!(calculator.get_result(), Some(2.0));
-    }
-    #[test]
-    fn test_mult() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("3");
-        calculator.apply_operation("*");
-        assert_eq!(calculator.get_result(), Some(15.0));
-    }
-    #[test]
-    fn test_divide() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("5");
-        calculator.apply_operation("/");
-        assert_eq!(calculator.get_result(), Some(1.0));
-    }
-    #[test]
-    fn test_exponent() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("2");
-        calculator.apply_operation("^");
-        assert_eq!(calculator.get_result(), Some(25.0));
+// Draws the function of x on top of the stack in the terminal, or into an SVG file if a path is given
+fn print_plot(calc: &RPNCalculator, xmin: &str, xmax: &str, path: Option<&str>) {
+    let (Ok(xmin), Ok(xmax)) = (xmin.parse::<f64>(), xmax.parse::<f64>()) else {
+        println!("'plot' needs the range as two numbers, like 'plot -2 2'.");
+        return;
+    };
+    match (calc.plot(xmin, xmax, PLOT_SAMPLES), path) {
+        (Err(error), _) => println!("{}", error),
+        (Ok(plot), None) => println!("{}", plot.to_braille(PLOT_COLUMNS, PLOT_ROWS)),
+        (Ok(plot), Some(path)) => match fs::write(path, plot.to_svg(SVG_WIDTH, SVG_HEIGHT)) {
+            Ok(()) => println!("Plot saved to '{}'.", path),
+            Err(error) => println!("Cannot save '{}': {}", path, error),
+        },
     }
+}
 
-    #[test]
-    fn test_log() {
-        let mut calculator: RPNCalculator = RPNCalculator::new();
-        calculator.stack.push(10.0);
-        calculator.apply_operation("log");
-        assert_eq!(calculator.stack.pop(), Some(1.0));
-    }
-    #[test]
-    fn test_abs() {
-        let mut calculator: RPNCalculator = RPNCalculator::new();
-        calculator.stack.push(16.0);
-        calculator.apply_operation("abs");
-        assert_eq!(calculator.stack.pop(), Some(16.0));
-    }
-    #[test]
-    fn test_sqr() {
-        let mut calculator: RPNCalculator = RPNCalculator::new();
-        calculator.stack.push(4.0);
-        calculator.apply_operation("sqrt");
-        assert_eq!(calculator.stack.pop(), Some(2.0));
+// Shows the RPN tokens of an infix expression together with the result, returns false on errors
+fn evaluate_infix_input(calc: &mut RPNCalculator, expression: &str) -> bool {
+    match calc.evaluate_infix(expression) {
+        Ok(tokens) => {
+            println!("RPN: {}", tokens.join(" "));
+            print_current_result(calc);
+            true
+        }
+        Err(error) => {
+            println!("{}", error);
+            false
+        }
     }
+}
 
-    #[test]
-    fn test_factorial_operation_handling() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(5.0);
-        calculator.factorial_operation_handling();
-        assert_eq!(calculator.stack.pop(), Some(120.0));
-    }
+// Evaluates the recorded lines of earlier sessions, lines that fail now are skipped.
+// Returns the number of lines that were restored
+fn replay_history(calc: &mut RPNCalculator, path: &Path) -> usize {
+    let Ok(text) = fs::read_to_string(path) else { return 0 };
+    text.lines()
+        .filter(|line| match line.strip_prefix("infix ") {
+            Some(expression) => calc.evaluate_infix(expression).is_ok(),
+            None => calc.evaluate_line(line).is_ok(),
+        })
+        .count()
+}
 
-    #[test]
-    fn test_full_stack_addition_handling() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(1.0);
-        calculator.stack.push(2.0);
-        calculator.stack.push(3.0);
-        calculator.full_stack_addition_handling();
-        assert_eq!(calculator.stack.pop(), Some(6.0));
+// A history that cannot be written should not stop the calculator, so errors are ignored.
+// Once the file is full the oldest lines are dropped
+fn record_history(path: Option<&Path>, line: &str) {
+    let Some(path) = path else { return };
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() < HISTORY_LINES {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{}", line);
+        }
+        return;
     }
+    let mut kept = lines[lines.len() + 1 - HISTORY_LINES..].join("\n");
+    kept.push('\n');
+    kept.push_str(line);
+    kept.push('\n');
+    let _ = fs::write(path, kept);
+}
 
-    #[test]
-    fn test_full_stack_multiplication_handling() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(2.0);
-        calculator.stack.push(3.0);
-        calculator.full_stack_multiplication_handling();
-        assert_eq!(calculator.stack.pop(), Some(6.0));
+// Every stack entry with its value, infix and LaTeX form, the top of the stack is printed last
+fn print_entries(calc: &RPNCalculator) {
+    let entries = calc.entries();
+    if entries.is_empty() {
+        println!("The stack is empty.");
     }
-
-    #[test]
-    fn test_new_number_handling() {
-        let mut calculator = RPNCalculator::new();
-        calculator.new_number_handling("42");
-        assert_eq!(calculator.stack.pop(), Some(42.0));
+    for (index, entry) in entries.iter().enumerate() {
+        println!("{}: {}", entries.len() - index, calc.format_value(&entry.value));
+        println!("   infix: {}", entry.expr.to_infix_with(calc.operators()));
+        println!("   LaTeX: {}", entry.expr.to_latex_with(calc.operators()));
     }
+}
 
-    #[test]
-    fn test_welcome_prompt() {
-        // This function only prints output, so we can't directly test it.
-        // You can manually verify the output if needed.
+fn print_definitions(calc: &RPNCalculator) {
+    let mut empty = true;
+    for (name, body) in calc.definitions() {
+        println!(": {} {} ;", name, body.join(" "));
+        empty = false;
+    }
+    if empty {
+        println!("No words defined.");
     }
+}
 
-    #[test]
-    fn test_get_result() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(42.0);
-        assert_eq!(calculator.get_result(), Some(42.0));
+fn print_variables(calc: &RPNCalculator) {
+    let mut empty = true;
+    for (name, value) in calc.variables() {
+        println!("{} = {}", name, calc.format_value(value));
+        empty = false;
+    }
+    if empty {
+        println!("No variables stored.");
     }
+}
 
-    #[test]
-    fn test_main() {
-        // Simulate input and output for the main function.
-        // This requires integration testing or mocking stdin/stdout.
+// Non-interactive mode: evaluates the whole input, prints the final stack and exits non-zero on errors
+fn run_batch_and_exit<R: BufRead>(mut calc: RPNCalculator, reader: R, infix: bool) -> ! {
+    match run_batch(&mut calc, reader, infix) {
+        Ok(()) => {
+            let values: Vec<String> = calc.stack().iter().map(|value| calc.format_value(value)).collect();
+            println!("{}", values.join(" "));
+            process::exit(0);
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
     }
+}
 
+// Evaluates a script line by line, '#' starts a comment and 'exit' stops early
+fn run_batch<R: BufRead>(calc: &mut RPNCalculator, reader: R, infix: bool) -> Result<(), String> {
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| format!("Cannot read input: {}", error))?;
+        let line = line.split('#').next().unwrap_or("").trim();
 
-    #[test]
-    fn test_apply_operation_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.apply_operation("5");
-        calculator.apply_operation("3");
-        calculator.apply_operation("+");
-        assert_eq!(calculator.get_result(), Some(8.0));
+        if line.eq_ignore_ascii_case("exit") {
+            break;
+        }
+        let result = if infix && !line.is_empty() {
+            calc.evaluate_infix(line).map(|_| ())
+        } else {
+            calc.evaluate_line(line)
+        };
+        result.map_err(|error| format!("Line {}: {}", index + 1, error))?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_arithmetical_operation_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(5.0);
-        calculator.stack.push(3.0);
-        calculator.arithmetical_operation_handling("+");
-        assert_eq!(calculator.stack.pop(), Some(8.0));
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn test_log_abs_sqrt_operation_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(16.0);
-        calculator.log_abs_sqrt_operation_handling("sqrt");
-        assert_eq!(calculator.stack.pop(), Some(4.0));
+    fn test_run_batch() {
+        let mut calc = RPNCalculator::new();
+        let script = "5 3 + # comment\n\n2 *\nexit\n1 +\n";
+        run_batch(&mut calc, script.as_bytes(), false).unwrap();
+        assert_eq!(calc.stack(), &[16.0]);
     }
 
     #[test]
-    fn test_factorial_operation_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(5.0);
-        calculator.factorial_operation_handling();
-        assert_eq!(calculator.stack.pop(), Some(120.0));
+    fn test_run_batch_infix() {
+        let mut calc = RPNCalculator::new();
+        let script = "(5 + 3) * sqrt(16) # comment\n\n-2 ^ 2\n";
+        run_batch(&mut calc, script.as_bytes(), true).unwrap();
+        assert_eq!(calc.stack(), &[32.0, -4.0]);
+        let result = run_batch(&mut calc, "1 +\n".as_bytes(), true);
+        assert_eq!(result, Err("Line 1: Invalid expression: incomplete expression".to_owned()));
     }
 
     #[test]
-    fn test_full_stack_addition_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(1.0);
-        calculator.stack.push(2.0);
-        calculator.stack.push(3.0);
-        calculator.full_stack_addition_handling();
-        assert_eq!(calculator.stack.pop(), Some(6.0));
-    }
+    fn test_save_and_load_session() {
+        let path = env::temp_dir().join("rpn-calculator-test-session.rpn");
+        let path = path.to_str().unwrap();
+        let mut calc = RPNCalculator::new();
+        calc.evaluate_line(": sq dup * ; : hyp sq swap sq + sqrt ; 3 =a exact 1/2").unwrap();
+        assert!(handle_session_command(&mut calc, &format!("save {}", path)));
 
-    #[test]
-    fn test_full_stack_multiplication_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(2.0);
-        calculator.stack.push(3.0);
-        calculator.full_stack_multiplication_handling();
-        assert_eq!(calculator.stack.pop(), Some(6.0));
+        let mut loaded = RPNCalculator::new();
+        assert!(handle_session_command(&mut loaded, &format!("load {}", path)));
+        assert_eq!(loaded.entries(), calc.entries());
+        loaded.evaluate_line("float $a 4 hyp").unwrap();
+        assert_eq!(loaded.stack(), &[0.5, 5.0]);
+        fs::remove_file(path).unwrap();
     }
 
     #[test]
-    fn test_new_number_handling_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.new_number_handling("42");
-        assert_eq!(calculator.stack.pop(), Some(42.0));
+    fn test_plot_to_svg() {
+        let path = env::temp_dir().join("rpn-calculator-test-plot.svg");
+        let path = path.to_str().unwrap();
+        let mut calc = RPNCalculator::new();
+        calc.evaluate_line("x sin").unwrap();
+        assert!(handle_session_command(&mut calc, &format!("plot -3.2 3.2 {}", path)));
+        let svg = fs::read_to_string(path).unwrap();
+        assert!(svg.starts_with("<svg ") && svg.contains(">y = sin(x)</text>"));
+        assert!(handle_session_command(&mut calc, "plot a b"));
+        assert_eq!(calc.stack().len(), 1);
+        fs::remove_file(path).unwrap();
     }
 
     #[test]
-    fn test_get_result_again() {
-        let mut calculator = RPNCalculator::new();
-        calculator.stack.push(42.0);
-        assert_eq!(calculator.get_result(), Some(42.0));
+    fn test_replay_history() {
+        let path = env::temp_dir().join("rpn-calculator-test-history");
+        let _ = fs::remove_file(&path);
+        record_history(Some(&path), "5 =a");
+        record_history(Some(&path), "infix 2 * a");
+        record_history(Some(&path), "drop drop");
+        record_history(Some(&path), "$a 1 +");
+
+        let mut calc = RPNCalculator::new();
+        assert_eq!(replay_history(&mut calc, &path), 3);
+        assert_eq!(calc.stack(), &[10.0, 6.0]);
+        assert_eq!(calc.infix(), "a + 1");
+
+        // Only the last lines are kept
+        for number in 0..HISTORY_LINES + 5 {
+            record_history(Some(&path), &number.to_string());
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), HISTORY_LINES);
+        assert_eq!(text.lines().next(), Some("5"));
+        assert!(text.ends_with(&format!("\n{}\n", HISTORY_LINES + 4)));
+        fs::remove_file(&path).unwrap();
     }
 
     #[test]
-    fn test_main_again() {
-        // Simulate input and output for the main function.
-        // This requires integration testing or mocking stdin/stdout.
+    fn test_run_batch_error() {
+        let mut calc = RPNCalculator::new();
+        let result = run_batch(&mut calc, "5 3 +\n+\n".as_bytes(), false);
+        assert_eq!(result, Err("Line 2: Stack underflow: '+' needs 2 value(s), but only 1 available".to_owned()));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,445 @@
+// Vector and matrix literals and the linear algebra on them. Elements are ordinary values,
+// so exact vectors stay exact and the arithmetic goes through RPNCalculator::combine
+use crate::bigint::BigInt;
+use crate::calculator::RPNCalculator;
+use crate::error::CalcError;
+use crate::rational::Rational;
+use crate::value::Value;
+
+// Structure of a bracketed literal, with any kind of element
+pub(crate) enum Array<T> {
+    Vector(Vec<T>),
+    Matrix(Vec<Vec<T>>),
+}
+
+impl<T> Array<T> {
+    pub(crate) fn map<U>(&self, f: impl Fn(&T) -> U) -> Array<U> {
+        match self {
+            Array::Vector(elements) => Array::Vector(elements.iter().map(&f).collect()),
+            Array::Matrix(rows) => Array::Matrix(rows.iter().map(|row| row.iter().map(&f).collect()).collect()),
+        }
+    }
+}
+
+// A matrix is a list of rows, so no literal nests deeper than this
+const MAX_DEPTH: usize = 2;
+
+enum Piece {
+    Open,
+    Close,
+    Atom(String),
+}
+
+enum Item<T> {
+    Atom(T),
+    List(Vec<Item<T>>),
+}
+
+// Change of the bracket nesting by a word, a literal is complete when the sum is back to zero
+pub(crate) fn bracket_depth(word: &str) -> i64 {
+    word.chars().map(|c| if c == '[' { 1 } else if c == ']' { -1 } else { 0 }).sum()
+}
+
+// Parses "[1 2 3]" and "[[1 2][3 4]]", elements are separated by whitespace or commas.
+// Matrices need rows of equal length, nothing may be empty
+pub(crate) fn parse_array<T>(text: &str, element: impl Fn(&str) -> Option<T>) -> Option<Array<T>> {
+    let mut pieces = Vec::new();
+    let mut atom = String::new();
+    for c in text.chars() {
+        if c == '[' || c == ']' || c == ',' || c.is_whitespace() {
+            if !atom.is_empty() {
+                pieces.push(Piece::Atom(std::mem::take(&mut atom)));
+            }
+            match c {
+                '[' => pieces.push(Piece::Open),
+                ']' => pieces.push(Piece::Close),
+                _ => {}
+            }
+        } else {
+            atom.push(c);
+        }
+    }
+    if !atom.is_empty() {
+        pieces.push(Piece::Atom(atom));
+    }
+
+    if !matches!(pieces.first(), Some(Piece::Open)) {
+        return None;
+    }
+    let mut position = 1;
+    let items = parse_list(&pieces, &mut position, 1, &element)?;
+    if position != pieces.len() || items.is_empty() {
+        return None;
+    }
+
+    if items.iter().all(|item| matches!(item, Item::Atom(_))) {
+        let elements = items.into_iter().map(|item| match item {
+            Item::Atom(value) => value,
+            Item::List(_) => unreachable!(),
+        });
+        return Some(Array::Vector(elements.collect()));
+    }
+    let mut rows = Vec::new();
+    for item in items {
+        let Item::List(row) = item else { return None };
+        let row: Option<Vec<T>> = row
+            .into_iter()
+            .map(|item| match item {
+                Item::Atom(value) => Some(value),
+                Item::List(_) => None,
+            })
+            .collect();
+        rows.push(row?);
+    }
+    let columns = rows[0].len();
+    if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+        return None;
+    }
+    Some(Array::Matrix(rows))
+}
+
+// Items up to the closing bracket of an already opened list, depth is the number of open brackets
+fn parse_list<T>(
+    pieces: &[Piece],
+    position: &mut usize,
+    depth: usize,
+    element: &impl Fn(&str) -> Option<T>,
+) -> Option<Vec<Item<T>>> {
+    let mut items = Vec::new();
+    loop {
+        let piece = pieces.get(*position)?;
+        *position += 1;
+        match piece {
+            Piece::Close => return Some(items),
+            Piece::Open if depth == MAX_DEPTH => return None,
+            Piece::Open => items.push(Item::List(parse_list(pieces, position, depth + 1, element)?)),
+            Piece::Atom(text) => items.push(Item::Atom(element(text)?)),
+        }
+    }
+}
+
+// Literal without whitespace, so it can be kept as a single token: "[[1,2],[3,4]]"
+pub(crate) fn canonical(text: &str) -> Option<String> {
+    Some(format_array(&parse_array(text, |element| Some(element.to_owned()))?, ","))
+}
+
+pub(crate) fn format_array<T: ToString>(array: &Array<T>, separator: &str) -> String {
+    let row = |elements: &[T]| {
+        let elements: Vec<String> = elements.iter().map(T::to_string).collect();
+        format!("[{}]", elements.join(separator))
+    };
+    match array {
+        Array::Vector(elements) => row(elements),
+        Array::Matrix(rows) => {
+            let rows: Vec<String> = rows.iter().map(|elements| row(elements)).collect();
+            format!("[{}]", rows.join(separator))
+        }
+    }
+}
+
+pub(crate) fn from_value(value: &Value) -> Option<Array<Value>> {
+    match value {
+        Value::Vector(elements) => Some(Array::Vector(elements.clone())),
+        Value::Matrix(rows) => Some(Array::Matrix(rows.clone())),
+        _ => None,
+    }
+}
+
+pub(crate) fn to_value(array: Array<Value>) -> Value {
+    match array {
+        Array::Vector(elements) => Value::Vector(elements),
+        Array::Matrix(rows) => Value::Matrix(rows),
+    }
+}
+
+// Size of a value for error messages: "3-vector", "2x3 matrix" or "number"
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Vector(elements) => format!("{}-vector", elements.len()),
+        Value::Matrix(rows) => format!("{}x{} matrix", rows.len(), rows[0].len()),
+        _ => "number".to_owned(),
+    }
+}
+
+fn size_error(token: &str, a: &Value, b: &Value) -> CalcError {
+    CalcError::DomainError(format!("'{}' cannot combine a {} and a {}", token, describe(a), describe(b)))
+}
+
+fn map<T>(elements: &[T], f: impl Fn(&T) -> Result<Value, CalcError>) -> Result<Vec<Value>, CalcError> {
+    elements.iter().map(f).collect()
+}
+
+// Applies a binary operator element by element, a number is combined with every element
+pub(crate) fn elementwise(token: &str, a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let combine = |x: &Value, y: &Value| RPNCalculator::combine(token, x, y);
+    let zip = |x: &[Value], y: &[Value]| -> Result<Vec<Value>, CalcError> {
+        if x.len() != y.len() {
+            return Err(size_error(token, a, b));
+        }
+        x.iter().zip(y).map(|(x, y)| combine(x, y)).collect()
+    };
+    Ok(match (a, b) {
+        (Value::Vector(x), Value::Vector(y)) => Value::Vector(zip(x, y)?),
+        (Value::Matrix(x), Value::Matrix(y)) if x.len() == y.len() => {
+            Value::Matrix(x.iter().zip(y).map(|(x, y)| zip(x, y)).collect::<Result<_, _>>()?)
+        }
+        (Value::Vector(x), y) if !y.is_array() => Value::Vector(map(x, |x| combine(x, y))?),
+        (x, Value::Vector(y)) if !x.is_array() => Value::Vector(map(y, |y| combine(x, y))?),
+        (Value::Matrix(x), y) if !y.is_array() => {
+            Value::Matrix(x.iter().map(|row| map(row, |x| combine(x, y))).collect::<Result<_, _>>()?)
+        }
+        (x, Value::Matrix(y)) if !x.is_array() => {
+            Value::Matrix(y.iter().map(|row| map(row, |y| combine(x, y))).collect::<Result<_, _>>()?)
+        }
+        _ => return Err(size_error(token, a, b)),
+    })
+}
+
+fn sum_of_products(x: &[Value], y: &[Value]) -> Result<Value, CalcError> {
+    let mut products = x.iter().zip(y).map(|(x, y)| RPNCalculator::combine("*", x, y));
+    let first = products.next().unwrap()?;
+    products.try_fold(first, |sum, product| RPNCalculator::combine("+", &sum, &product?))
+}
+
+fn columns(rows: &[Vec<Value>]) -> Vec<Vec<Value>> {
+    (0..rows[0].len()).map(|column| rows.iter().map(|row| row[column].clone()).collect()).collect()
+}
+
+// Scalar product of vectors, otherwise the matrix product with vectors as columns or rows
+pub(crate) fn dot(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let error = || size_error("dot", a, b);
+    match (a, b) {
+        (Value::Vector(x), Value::Vector(y)) if x.len() == y.len() => sum_of_products(x, y),
+        (Value::Matrix(x), Value::Vector(y)) if x[0].len() == y.len() => {
+            Ok(Value::Vector(map(x, |row| sum_of_products(row.as_slice(), y))?))
+        }
+        (Value::Vector(x), Value::Matrix(y)) if x.len() == y.len() => {
+            Ok(Value::Vector(map(&columns(y), |column| sum_of_products(x, column.as_slice()))?))
+        }
+        (Value::Matrix(x), Value::Matrix(y)) if x[0].len() == y.len() => {
+            let columns = columns(y);
+            let rows = x.iter().map(|row| map(&columns, |column| sum_of_products(row, column.as_slice())));
+            Ok(Value::Matrix(rows.collect::<Result<_, _>>()?))
+        }
+        _ => Err(error()),
+    }
+}
+
+pub(crate) fn cross(a: &Value, b: &Value) -> Result<Value, CalcError> {
+    match (a, b) {
+        (Value::Vector(x), Value::Vector(y)) if x.len() == 3 && y.len() == 3 => {
+            let component = |i: usize, j: usize| {
+                let left = RPNCalculator::combine("*", &x[i], &y[j])?;
+                let right = RPNCalculator::combine("*", &x[j], &y[i])?;
+                RPNCalculator::combine("-", &left, &right)
+            };
+            Ok(Value::Vector(vec![component(1, 2)?, component(2, 0)?, component(0, 1)?]))
+        }
+        _ => Err(CalcError::DomainError(format!(
+            "'cross' needs two 3-vectors, got a {} and a {}",
+            describe(a),
+            describe(b)
+        ))),
+    }
+}
+
+fn square_rows<'a>(token: &str, value: &'a Value) -> Result<&'a [Vec<Value>], CalcError> {
+    match value {
+        Value::Matrix(rows) if rows.len() == rows[0].len() => Ok(rows),
+        _ => Err(CalcError::DomainError(format!("'{}' needs a square matrix, got a {}", token, describe(value)))),
+    }
+}
+
+fn integer(value: i64) -> Value {
+    Value::Exact(Rational::from_integer(BigInt::from(value)))
+}
+
+// The largest element is used as pivot, which keeps the floating point error small
+fn pivot_row(rows: &[Vec<Value>], column: usize) -> usize {
+    (column..rows.len())
+        .max_by(|&i, &j| rows[i][column].to_complex().abs().total_cmp(&rows[j][column].to_complex().abs()))
+        .unwrap()
+}
+
+fn is_zero(value: &Value) -> bool {
+    value.to_complex().abs() == 0.0
+}
+
+// Gaussian elimination, the determinant is the product of the pivots
+pub(crate) fn determinant(value: &Value) -> Result<Value, CalcError> {
+    let mut rows = square_rows("det", value)?.to_vec();
+    let size = rows.len();
+    let mut result = integer(1);
+    for column in 0..size {
+        let pivot = pivot_row(&rows, column);
+        if is_zero(&rows[pivot][column]) {
+            return Ok(integer(0));
+        }
+        if pivot != column {
+            rows.swap(pivot, column);
+            result = RPNCalculator::combine("*", &result, &integer(-1))?;
+        }
+        result = RPNCalculator::combine("*", &result, &rows[column][column])?;
+        let pivot_entries = rows[column].clone();
+        for row in rows.iter_mut().skip(column + 1) {
+            let factor = RPNCalculator::combine("/", &row[column], &pivot_entries[column])?;
+            for (entry, pivot) in row.iter_mut().zip(&pivot_entries).skip(column) {
+                let product = RPNCalculator::combine("*", &factor, pivot)?;
+                *entry = RPNCalculator::combine("-", entry, &product)?;
+            }
+        }
+    }
+    Ok(result)
+}
+
+// Gauss-Jordan elimination on the matrix next to the identity matrix
+pub(crate) fn inverse(value: &Value) -> Result<Value, CalcError> {
+    let size = square_rows("inv", value)?.len();
+    let mut rows: Vec<Vec<Value>> = square_rows("inv", value)?
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let identity = (0..size).map(|column| integer(i64::from(column == index)));
+            row.iter().cloned().chain(identity).collect()
+        })
+        .collect();
+
+    for column in 0..size {
+        let pivot = pivot_row(&rows, column);
+        if is_zero(&rows[pivot][column]) {
+            return Err(CalcError::DomainError("matrix is singular".to_owned()));
+        }
+        rows.swap(pivot, column);
+        let divisor = rows[column][column].clone();
+        rows[column] = map(&rows[column], |entry| RPNCalculator::combine("/", entry, &divisor))?;
+        let pivot_entries = rows[column].clone();
+        for (index, row) in rows.iter_mut().enumerate() {
+            if index == column {
+                continue;
+            }
+            let factor = row[column].clone();
+            for (entry, pivot) in row.iter_mut().zip(&pivot_entries) {
+                let product = RPNCalculator::combine("*", &factor, pivot)?;
+                *entry = RPNCalculator::combine("-", entry, &product)?;
+            }
+        }
+    }
+    Ok(Value::Matrix(rows.into_iter().map(|row| row[size..].to_vec()).collect()))
+}
+
+// A vector becomes a single column
+pub(crate) fn transpose(value: &Value) -> Result<Value, CalcError> {
+    match value {
+        Value::Vector(elements) => Ok(Value::Matrix(elements.iter().map(|element| vec![element.clone()]).collect())),
+        Value::Matrix(rows) => Ok(Value::Matrix(columns(rows))),
+        _ => Err(CalcError::DomainError(format!("'transpose' needs a vector or matrix, got a {}", describe(value)))),
+    }
+}
+
+// Euclidean norm of a vector and Frobenius norm of a matrix, exact if the result is rational
+pub(crate) fn norm(value: &Value) -> Result<Value, CalcError> {
+    let elements: Vec<Value> = match value {
+        Value::Vector(elements) => elements.clone(),
+        Value::Matrix(rows) => rows.concat(),
+        _ => return Err(CalcError::DomainError(format!("'norm' needs a vector or matrix, got a {}", describe(value)))),
+    };
+    let squares = map(&elements, |element| match element {
+        Value::Complex(z) => Ok(Value::Float(z.abs() * z.abs())),
+        _ => RPNCalculator::combine("*", element, element),
+    })?;
+    let sum = squares.iter().skip(1).try_fold(squares[0].clone(), |sum, square| {
+        RPNCalculator::combine("+", &sum, square)
+    })?;
+    Ok(match &sum {
+        Value::Exact(x) => x.exact_sqrt().map(Value::Exact).unwrap_or(Value::Float(x.to_f64().sqrt())),
+        _ => Value::Float(sum.to_f64().sqrt()),
+    })
+}
+
+// Sum or product along a vector, matrices are reduced column by column to a vector
+pub(crate) fn reduce(token: &str, value: &Value) -> Result<Value, CalcError> {
+    let fold = |elements: &[Value]| {
+        elements.iter().skip(1).try_fold(elements[0].clone(), |acc, element| {
+            RPNCalculator::combine(token, &acc, element)
+        })
+    };
+    match value {
+        Value::Vector(elements) => fold(elements),
+        Value::Matrix(rows) => Ok(Value::Vector(map(&columns(rows), |column| fold(column))?)),
+        _ => unreachable!(),
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str) -> Value {
+        to_value(parse_array(text, |element| Rational::parse(element).map(Value::Exact)).unwrap())
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        assert_eq!(canonical("[1 2  3]").unwrap(), "[1,2,3]");
+        assert_eq!(canonical("[[1 2][3, 4]]").unwrap(), "[[1,2],[3,4]]");
+        assert_eq!(canonical("[[1 2] [3]]"), None);
+        assert_eq!(canonical("[1 [2]]"), None);
+        assert_eq!(canonical("[]"), None);
+        assert_eq!(canonical("[1 2"), None);
+        assert_eq!(canonical("[1 2]]"), None);
+        assert_eq!(canonical("[[[1]]]"), None);
+        assert_eq!(canonical(&format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000))), None);
+        assert_eq!(bracket_depth("[[1"), 2);
+        assert_eq!(value("[[1 2] [3 4]]").to_string(), "[[1 2] [3 4]]");
+    }
+
+    #[test]
+    fn test_elementwise() {
+        assert_eq!(elementwise("+", &value("[1 2 3]"), &value("[10 20 30]")), Ok(value("[11 22 33]")));
+        assert_eq!(elementwise("*", &integer(2), &value("[[1 2] [3 4]]")), Ok(value("[[2 4] [6 8]]")));
+        assert_eq!(elementwise("/", &value("[1 2]"), &integer(4)), Ok(value("[1/4 1/2]")));
+        assert_eq!(
+            elementwise("+", &value("[1 2]"), &value("[1 2 3]")),
+            Err(CalcError::DomainError("'+' cannot combine a 2-vector and a 3-vector".to_owned()))
+        );
+        assert!(elementwise("+", &value("[[1 2]]"), &value("[[1] [2]]")).is_err());
+    }
+
+    #[test]
+    fn test_products() {
+        assert_eq!(dot(&value("[1 2 3]"), &value("[4 5 6]")), Ok(integer(32)));
+        assert_eq!(dot(&value("[[1 2] [3 4]]"), &value("[1 1]")), Ok(value("[3 7]")));
+        assert_eq!(dot(&value("[1 1]"), &value("[[1 2] [3 4]]")), Ok(value("[4 6]")));
+        assert_eq!(dot(&value("[[1 2] [3 4]]"), &value("[[0 1] [1 0]]")), Ok(value("[[2 1] [4 3]]")));
+        assert!(dot(&value("[1 2]"), &value("[1 2 3]")).is_err());
+        assert_eq!(cross(&value("[1 0 0]"), &value("[0 1 0]")), Ok(value("[0 0 1]")));
+        assert!(cross(&value("[1 0]"), &value("[0 1]")).is_err());
+    }
+
+    #[test]
+    fn test_determinant_and_inverse() {
+        assert_eq!(determinant(&value("[[1 2] [3 4]]")), Ok(integer(-2)));
+        assert_eq!(determinant(&value("[[0 1 2] [1 0 3] [4 -3 8]]")), Ok(integer(-2)));
+        assert_eq!(determinant(&value("[[1 2] [2 4]]")), Ok(integer(0)));
+        assert_eq!(inverse(&value("[[1 2] [3 4]]")), Ok(value("[[-2 1] [3/2 -1/2]]")));
+        assert_eq!(
+            inverse(&value("[[1 2] [2 4]]")),
+            Err(CalcError::DomainError("matrix is singular".to_owned()))
+        );
+        assert!(determinant(&value("[[1 2 3] [4 5 6]]")).is_err());
+        assert!(inverse(&value("[1 2]")).is_err());
+    }
+
+    #[test]
+    fn test_transpose_norm_and_reduce() {
+        assert_eq!(transpose(&value("[[1 2 3] [4 5 6]]")), Ok(value("[[1 4] [2 5] [3 6]]")));
+        assert_eq!(transpose(&value("[1 2]")), Ok(value("[[1] [2]]")));
+        assert_eq!(norm(&value("[3 4]")), Ok(integer(5)));
+        assert_eq!(norm(&value("[[1 1] [1 1]]")), Ok(integer(2)));
+        assert_eq!(norm(&value("[1 1]")), Ok(Value::Float(2f64.sqrt())));
+        assert_eq!(reduce("+", &value("[1 2 3]")), Ok(integer(6)));
+        assert_eq!(reduce("*", &value("[[1 2] [3 4]]")), Ok(value("[3 8]")));
+    }
+}
@@ -0,0 +1,687 @@
+// Registry of every operator: number of operands, how it is evaluated and how it is written in infix
+// and LaTeX. Evaluation, the reconstructed expressions and the infix parser all look operators up here,
+// further operators can be added from a config file at startup
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use crate::calculator::{self, RPNCalculator, Rearrangement};
+use crate::error::CalcError;
+use crate::expression::Expr;
+use crate::matrix;
+use crate::rational::Rational;
+use crate::statistics;
+use crate::symbolic;
+use crate::value::Value;
+
+// Binding strength of the printed forms, higher binds tighter
+pub(crate) const CONVERSION: u8 = 0;
+pub(crate) const SUM: u8 = 1;
+pub(crate) const PRODUCT: u8 = 2;
+pub(crate) const NEGATION: u8 = 3;
+pub(crate) const POWER: u8 = 4;
+pub(crate) const FACTORIAL: u8 = 5;
+pub(crate) const ATOM: u8 = 6;
+
+// Method of the calculator that works on the stack itself, called with the operator name
+pub(crate) type Handler = fn(&mut RPNCalculator, &str) -> Result<(), CalcError>;
+
+// Result of an operator from the values of its operands, the deepest operand comes first.
+// The name is only for error messages
+pub(crate) type Function = fn(&RPNCalculator, &str, &[Value]) -> Result<Value, CalcError>;
+
+// Derivative of a function of one operand from the operand u, its derivative du and whether angles are
+// in degrees
+pub(crate) type Rule = fn(&Expr, Expr, bool) -> Expr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Arity {
+    Fixed(usize),
+    // The whole stack like the statistics, with at least the given number of values
+    Stack(usize),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Evaluation {
+    // The operands are taken from the stack and replaced by the result
+    Function(Function),
+    // Works on the stack itself, like 'linreg' that leaves two values
+    Builtin(Handler),
+    // Tokens that are run on the operands, for operators from the config file
+    Rpn(Vec<String>),
+    // Only written in expressions, as the result of another word like 'slope' of 'linreg'
+    Derived,
+    // Words like 'dup' or 'pi' that work on the stack itself, they are never written as an operation
+    Word(Handler),
+}
+
+// Values besides real numbers that an operator takes, operands of the other kinds are rejected before it runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Accepts {
+    pub complex: bool,
+    pub arrays: bool,
+    pub quantities: bool,
+    // Expressions of x
+    pub expressions: bool,
+}
+
+impl Accepts {
+    pub(crate) const REAL: Accepts = Accepts { complex: false, arrays: false, quantities: false, expressions: false };
+    pub(crate) const NUMBERS: Accepts = Accepts { complex: true, ..Accepts::REAL };
+    pub(crate) const ALL: Accepts = Accepts { complex: true, arrays: true, quantities: true, expressions: true };
+
+    pub(crate) fn contains(self, value: &Value) -> bool {
+        (self.complex || !value.is_complex())
+            && (self.arrays || !value.is_array())
+            && (self.quantities || !value.is_quantity())
+            && (self.expressions || !value.is_symbolic())
+    }
+}
+
+// The templates have '$0', '$1', ... for the operands and '$*' for all of them separated by commas.
+// An operand at the start or the end of a template gets parentheses if it binds weaker than the
+// operator, '$(0)' gets them unless it is a single number or name
+#[derive(Clone, Debug)]
+pub(crate) struct Operator {
+    pub arity: Arity,
+    pub evaluation: Evaluation,
+    pub accepts: Accepts,
+    pub infix: String,
+    pub latex: String,
+    pub precedence: u8,
+    pub right_associative: bool,
+    // Functions with a rule stay unevaluated on an expression of x
+    pub derivative: Option<Rule>,
+}
+
+// Piece of a template
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    // Index of the operand, true if it is in parentheses unless it is a leaf
+    Operand(usize, bool),
+    AllOperands,
+}
+
+// Operators of a calculator. Every calculator owns its registry, so operators from a config file only
+// exist where they were loaded
+#[derive(Clone, Debug)]
+pub struct Operators {
+    operators: BTreeMap<String, Operator>,
+}
+
+// The built-in operators alone, for expressions that are printed without a calculator
+static BUILTIN: LazyLock<Operators> = LazyLock::new(Operators::default);
+
+// Written like a function call, 'name(a, b)' in infix and with the given LaTeX name
+fn function(arity: usize, name: &str, latex_name: &str, function: Function) -> Operator {
+    Operator { evaluation: Evaluation::Function(function), ..derived(arity, name, latex_name) }
+}
+
+// Written like a function, but only as the result of another word like 'sum' of '++'
+fn derived(arity: usize, name: &str, latex_name: &str) -> Operator {
+    let (infix, latex) = call_templates(arity, name, latex_name);
+    Operator {
+        arity: Arity::Fixed(arity),
+        evaluation: Evaluation::Derived,
+        accepts: Accepts::NUMBERS,
+        infix,
+        latex,
+        precedence: ATOM,
+        right_associative: false,
+        derivative: None,
+    }
+}
+
+fn call_templates(arity: usize, name: &str, latex_name: &str) -> (String, String) {
+    let operands: Vec<String> = (0..arity).map(|index| format!("${}", index)).collect();
+    let infix = format!("{}({})", name, operands.join(", "));
+    // A single argument only gets parentheses if it is more than a leaf, "\sin x" but "\sin\left(x + 1\right)"
+    let latex = match arity {
+        1 => format!("{}$(0)", latex_name),
+        _ => format!(r"{}\left({}\right)", latex_name, operands.join(", ")),
+    };
+    (infix, latex)
+}
+
+// Written between or after its operands, with the given binding strength
+fn notation(arity: usize, precedence: u8, infix: &str, latex: &str, function: Function) -> Operator {
+    Operator {
+        arity: Arity::Fixed(arity),
+        evaluation: Evaluation::Function(function),
+        accepts: Accepts::NUMBERS,
+        infix: infix.to_owned(),
+        latex: latex.to_owned(),
+        precedence,
+        right_associative: false,
+        derivative: None,
+    }
+}
+
+// Not written at all, 'arity' is what the word takes from the stack
+fn word(arity: Arity, handler: Handler) -> Operator {
+    Operator {
+        arity,
+        evaluation: Evaluation::Word(handler),
+        // Words move any value
+        accepts: Accepts::ALL,
+        infix: String::new(),
+        latex: String::new(),
+        precedence: ATOM,
+        right_associative: false,
+        derivative: None,
+    }
+}
+
+// Of the whole stack, which needs at least 'minimum' real values
+fn statistic(name: &str, latex_name: &str, minimum: usize, function: Option<Function>) -> Operator {
+    Operator {
+        arity: Arity::Stack(minimum),
+        evaluation: function.map_or(Evaluation::Derived, Evaluation::Function),
+        accepts: Accepts::REAL,
+        infix: format!("{}($*)", name),
+        latex: format!(r"{}\left($*\right)", latex_name),
+        precedence: ATOM,
+        right_associative: false,
+        derivative: None,
+    }
+}
+
+fn builtins() -> BTreeMap<String, Operator> {
+    let arithmetic: Function = |_, token, values| RPNCalculator::combine(token, &values[0], &values[1]);
+    let symbolic = Accepts { expressions: true, ..Accepts::NUMBERS };
+    let arrays = Accepts { arrays: true, ..Accepts::NUMBERS };
+    // Vectors and matrices, but no single numbers
+    let linear_algebra = |operator: Operator| Operator { accepts: arrays, ..operator };
+
+    let operators = vec![
+        ("+", Operator { accepts: Accepts::ALL, ..notation(2, SUM, "$0 + $1", "$0 + $1", arithmetic) }),
+        ("-", Operator { accepts: Accepts::ALL, ..notation(2, SUM, "$0 - $1", "$0 - $1", arithmetic) }),
+        ("*", Operator { accepts: Accepts::ALL, ..notation(2, PRODUCT, "$0 * $1", r"$0 \cdot $1", arithmetic) }),
+        ("/", Operator { accepts: Accepts::ALL, ..notation(2, PRODUCT, "$0 / $1", r"\frac{$0}{$1}", arithmetic) }),
+        ("mod", Operator { accepts: arrays, ..notation(2, PRODUCT, "$0 mod $1", r"$0 \bmod $1", arithmetic) }),
+        ("^", Operator {
+            accepts: Accepts::ALL,
+            right_associative: true,
+            ..notation(2, POWER, "$0 ^ $1", "$(0)^{$1}", arithmetic)
+        }),
+        ("!", notation(1, FACTORIAL, "$0!", "$(0)!", RPNCalculator::factorial)),
+        ("min", function(2, "min", r"\min", |_, token, values| RPNCalculator::extremum(token, values, Ordering::Less))),
+        ("max", function(2, "max", r"\max", |_, token, values| {
+            RPNCalculator::extremum(token, values, Ordering::Greater)
+        })),
+        // 'cplx' is a function in infix, but a sum in LaTeX
+        ("cplx", Operator {
+            latex: "$0 + $(1)i".to_owned(),
+            precedence: SUM,
+            ..function(2, "cplx", "", RPNCalculator::complex_from_parts)
+        }),
+        ("sqrt", Operator {
+            latex: r"\sqrt{$0}".to_owned(),
+            derivative: Some(symbolic::square_root_derivative),
+            ..function(1, "sqrt", "", |_, _, values| Ok(RPNCalculator::square_root(&values[0])))
+        }),
+        ("log", Operator {
+            infix: "log10($0)".to_owned(),
+            derivative: Some(symbolic::common_logarithm_derivative),
+            ..function(1, "log", r"\log_{10}", |_, _, values| {
+                RPNCalculator::logarithm(&values[0], f64::log10, std::f64::consts::LN_10)
+            })
+        }),
+        ("abs", Operator {
+            latex: r"\left| $0 \right|".to_owned(),
+            derivative: Some(symbolic::absolute_value_derivative),
+            ..function(1, "abs", "", |_, _, values| Ok(RPNCalculator::absolute_value(&values[0])))
+        }),
+        ("sin", Operator {
+            derivative: Some(symbolic::sine_derivative),
+            ..function(1, "sin", r"\sin", |calc, token, values| calc.angle_function(token, &values[0], f64::sin))
+        }),
+        ("cos", Operator {
+            derivative: Some(symbolic::cosine_derivative),
+            ..function(1, "cos", r"\cos", |calc, token, values| calc.angle_function(token, &values[0], f64::cos))
+        }),
+        ("tan", Operator {
+            derivative: Some(symbolic::tangent_derivative),
+            ..function(1, "tan", r"\tan", |calc, token, values| calc.angle_function(token, &values[0], f64::tan))
+        }),
+        ("asin", Operator {
+            derivative: Some(symbolic::arcsine_derivative),
+            ..function(1, "asin", r"\arcsin", |calc, token, values| {
+                calc.inverse_angle_function(token, &values[0], f64::asin)
+            })
+        }),
+        ("acos", Operator {
+            derivative: Some(symbolic::arccosine_derivative),
+            ..function(1, "acos", r"\arccos", |calc, token, values| {
+                calc.inverse_angle_function(token, &values[0], f64::acos)
+            })
+        }),
+        ("atan", Operator {
+            derivative: Some(symbolic::arctangent_derivative),
+            ..function(1, "atan", r"\arctan", |calc, token, values| {
+                calc.inverse_angle_function(token, &values[0], f64::atan)
+            })
+        }),
+        ("atan2", function(2, "atan2", r"\operatorname{atan2}", RPNCalculator::two_argument_arctangent)),
+        ("ln", Operator {
+            derivative: Some(symbolic::natural_logarithm_derivative),
+            ..function(1, "ln", r"\ln", |_, _, values| RPNCalculator::logarithm(&values[0], f64::ln, 1.0))
+        }),
+        ("exp", Operator {
+            latex: "e^{$0}".to_owned(),
+            derivative: Some(symbolic::exponential_derivative),
+            ..function(1, "exp", "", |_, _, values| Ok(RPNCalculator::exponential(&values[0])))
+        }),
+        ("log2", Operator {
+            derivative: Some(symbolic::binary_logarithm_derivative),
+            ..function(1, "log2", r"\log_{2}", |_, _, values| {
+                RPNCalculator::logarithm(&values[0], f64::log2, std::f64::consts::LN_2)
+            })
+        }),
+        ("floor", Operator {
+            latex: r"\left\lfloor $0 \right\rfloor".to_owned(),
+            ..function(1, "floor", "", |_, token, values| {
+                RPNCalculator::rounding(token, &values[0], Rational::floor, f64::floor)
+            })
+        }),
+        ("ceil", Operator {
+            latex: r"\left\lceil $0 \right\rceil".to_owned(),
+            ..function(1, "ceil", "", |_, token, values| {
+                RPNCalculator::rounding(token, &values[0], Rational::ceil, f64::ceil)
+            })
+        }),
+        ("round", function(1, "round", r"\operatorname{round}", |_, token, values| {
+            RPNCalculator::rounding(token, &values[0], Rational::round, f64::round)
+        })),
+        ("re", function(1, "re", r"\operatorname{Re}", |_, _, values| Ok(RPNCalculator::real_part(&values[0])))),
+        ("im", function(1, "im", r"\operatorname{Im}", |_, _, values| {
+            Ok(RPNCalculator::imaginary_part(&values[0]))
+        })),
+        ("arg", function(1, "arg", r"\arg", |calc, _, values| Ok(calc.argument(&values[0])))),
+        ("conj", Operator {
+            latex: r"\overline{$0}".to_owned(),
+            ..function(1, "conj", "", |_, _, values| Ok(RPNCalculator::conjugate(&values[0])))
+        }),
+        ("dot", linear_algebra(function(2, "dot", r"\operatorname{dot}", |_, _, values| {
+            matrix::dot(&values[0], &values[1])
+        }))),
+        // 'cross' is a function in infix, but a product in LaTeX
+        ("cross", linear_algebra(Operator {
+            latex: r"$0 \times $1".to_owned(),
+            precedence: PRODUCT,
+            ..function(2, "cross", "", |_, _, values| matrix::cross(&values[0], &values[1]))
+        })),
+        ("det", linear_algebra(function(1, "det", r"\det", |_, _, values| matrix::determinant(&values[0])))),
+        ("inv", linear_algebra(Operator {
+            latex: "$(0)^{-1}".to_owned(),
+            ..function(1, "inv", "", |_, _, values| matrix::inverse(&values[0]))
+        })),
+        ("transpose", linear_algebra(Operator {
+            latex: "$(0)^{T}".to_owned(),
+            ..function(1, "transpose", "", |_, _, values| matrix::transpose(&values[0]))
+        })),
+        ("norm", linear_algebra(Operator {
+            latex: r"\left\| $0 \right\|".to_owned(),
+            ..function(1, "norm", "", |_, _, values| matrix::norm(&values[0]))
+        })),
+        // Written only, these are the results of '++' and '**' on a single vector or matrix
+        ("sum", derived(1, "sum", r"\sum")),
+        ("prod", derived(1, "prod", r"\prod")),
+        ("and", function(2, "and", r"\operatorname{and}", |_, token, values| {
+            RPNCalculator::bitwise(token, values, |a, b| a & b)
+        })),
+        ("or", function(2, "or", r"\operatorname{or}", |_, token, values| {
+            RPNCalculator::bitwise(token, values, |a, b| a | b)
+        })),
+        ("xor", function(2, "xor", r"\operatorname{xor}", |_, token, values| {
+            RPNCalculator::bitwise(token, values, |a, b| a ^ b)
+        })),
+        ("not", function(1, "not", r"\operatorname{not}", |_, token, values| {
+            RPNCalculator::bitwise(token, values, |a, _| !a)
+        })),
+        ("shl", function(2, "shl", r"\operatorname{shl}", |_, token, values| {
+            RPNCalculator::shift(token, values, true)
+        })),
+        ("shr", function(2, "shr", r"\operatorname{shr}", |_, token, values| {
+            RPNCalculator::shift(token, values, false)
+        })),
+        // 'min' and 'max' compare two values, 'smin' and 'smax' the whole stack
+        ("mean", statistic("mean", r"\bar{x}", 1, Some(|_, _, values| statistics::mean(values)))),
+        ("median", statistic("median", r"\tilde{x}", 1, Some(|_, _, values| statistics::median(values)))),
+        ("var", statistic("var", r"\sigma^{2}", 2, Some(|_, _, values| statistics::variance(values)))),
+        ("stddev", statistic("stddev", r"\sigma", 2, Some(|_, _, values| statistics::standard_deviation(values)))),
+        ("smin", statistic("smin", r"\min", 1, Some(|_, _, values| Ok(statistics::minimum(values))))),
+        ("smax", statistic("smax", r"\max", 1, Some(|_, _, values| Ok(statistics::maximum(values))))),
+        // The number of values on an empty stack is 0
+        ("count", statistic("count", "n", 0, Some(|_, _, values| Ok(statistics::count(values))))),
+        // 'linreg' leaves two values, the intercept and the slope of the pairs x1 y1 x2 y2 ...
+        ("linreg", Operator {
+            evaluation: Evaluation::Builtin(RPNCalculator::linear_regression_handling),
+            ..statistic("linreg", r"\operatorname{linreg}", 4, None)
+        }),
+        ("intercept", statistic("intercept", r"\operatorname{intercept}", 4, None)),
+        ("slope", statistic("slope", r"\operatorname{slope}", 4, None)),
+        ("diff", Operator { accepts: symbolic, ..function(1, "diff", r"\frac{d}{dx}", RPNCalculator::differentiate) }),
+        ("simp", Operator {
+            accepts: symbolic,
+            ..function(1, "simp", r"\operatorname{simp}", RPNCalculator::simplification)
+        }),
+        ("pi", word(Arity::Fixed(0), |calc, token| calc.constant_handling(token, std::f64::consts::PI))),
+        ("e", word(Arity::Fixed(0), |calc, token| calc.constant_handling(token, std::f64::consts::E))),
+        ("++", word(Arity::Stack(1), |calc, _| calc.full_stack_addition_handling())),
+        ("**", word(Arity::Stack(1), |calc, _| calc.full_stack_multiplication_handling())),
+        ("dup", word(Arity::Fixed(1), |calc, token| calc.stack_word_handling(token, Some(1), Rearrangement::Pick))),
+        ("drop", word(Arity::Fixed(1), |calc, token| calc.stack_word_handling(token, Some(1), Rearrangement::Drop))),
+        ("swap", word(Arity::Fixed(2), |calc, token| calc.stack_word_handling(token, Some(2), Rearrangement::Roll))),
+        ("over", word(Arity::Fixed(2), |calc, token| calc.stack_word_handling(token, Some(2), Rearrangement::Pick))),
+        ("rot", word(Arity::Fixed(3), |calc, token| calc.stack_word_handling(token, Some(3), Rearrangement::Roll))),
+        ("clear", word(Arity::Stack(0), |calc, token| calc.stack_word_handling(token, Some(0), Rearrangement::Clear))),
+        // The depth is on top of the stack
+        ("pick", word(Arity::Fixed(1), |calc, token| calc.stack_word_handling(token, None, Rearrangement::Pick))),
+        ("roll", word(Arity::Fixed(1), |calc, token| calc.stack_word_handling(token, None, Rearrangement::Roll))),
+    ];
+    operators.into_iter().map(|(name, operator)| (name.to_owned(), operator)).collect()
+}
+
+impl Default for Operators {
+    fn default() -> Self {
+        Operators { operators: builtins() }
+    }
+}
+
+impl Operators {
+    pub(crate) fn builtin() -> &'static Operators {
+        &BUILTIN
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Operator> {
+        self.operators.get(name)
+    }
+
+    // Operators that are part of expressions, the words are not
+    pub(crate) fn written(&self, name: &str) -> Option<&Operator> {
+        self.get(name).filter(|operator| !matches!(operator.evaluation, Evaluation::Word(_)))
+    }
+
+    // Everything that can be typed, the derived operators only show up in expressions
+    pub fn is_word(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|operator| !matches!(operator.evaluation, Evaluation::Derived))
+    }
+
+    // Words that take operands, unlike constants like 'pi'
+    pub fn is_operator(&self, name: &str) -> bool {
+        self.is_word(name) && self.get(name).is_some_and(|operator| operator.arity != Arity::Fixed(0))
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.operators.keys().map(String::as_str).filter(|name| self.is_word(name))
+    }
+
+    // Operator that is written as a function call in infix, found by its own name or by the name in the
+    // template like 'log10' for 'log'. Returns the operator and its number of arguments
+    pub(crate) fn function_call(&self, name: &str) -> Option<(String, usize)> {
+        self.operators.iter().find_map(|(token, operator)| {
+            let Arity::Fixed(arity) = operator.arity else { return None };
+            let called = operator.infix.strip_suffix(')')?.split_once('(')?.0;
+            let is_call = !called.is_empty() && called.chars().all(|c| c.is_alphanumeric() || c == '_');
+            let typed = !matches!(operator.evaluation, Evaluation::Derived);
+            (is_call && typed && (called == name || token == name)).then(|| (token.clone(), arity))
+        })
+    }
+
+    // Operator that is written between its two operands like '$0 mod $1' or after its single operand like
+    // '$0!' in infix, found by the symbol in its template. Returns the operator and its number of operands
+    pub(crate) fn infix_operator(&self, symbol: &str) -> Option<(String, usize)> {
+        self.operators.iter().find_map(|(token, operator)| {
+            let typed = !matches!(operator.evaluation, Evaluation::Derived);
+            let (written, arity) = match (operator.arity, pieces(&operator.infix).as_slice()) {
+                (Arity::Fixed(2), [Piece::Operand(0, _), Piece::Text(text), Piece::Operand(1, _)]) => (text.trim(), 2),
+                (Arity::Fixed(1), [Piece::Operand(0, _), Piece::Text(text)]) => (text.trim(), 1),
+                _ => return None,
+            };
+            (typed && written == symbol).then(|| (token.clone(), arity))
+        })
+    }
+}
+
+pub(crate) fn pieces(template: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(position) = rest.find('$') {
+        let after = &rest[position + 1..];
+        let (inner, wrapped) = match after.strip_prefix('(') {
+            Some(inner) => (inner, true),
+            None => (after, false),
+        };
+        let digits = inner.find(|c: char| !c.is_ascii_digit()).unwrap_or(inner.len());
+        let (piece, length) = match inner[..digits].parse() {
+            _ if !wrapped && after.starts_with('*') => (Piece::AllOperands, 2),
+            Ok(index) if !wrapped => (Piece::Operand(index, false), 1 + digits),
+            Ok(index) if inner[digits..].starts_with(')') => (Piece::Operand(index, true), 3 + digits),
+            // A '$' that is not a placeholder stays as it is
+            _ => {
+                pieces.push(Piece::Text(&rest[..position + 1]));
+                rest = after;
+                continue;
+            }
+        };
+        if position > 0 {
+            pieces.push(Piece::Text(&rest[..position]));
+        }
+        pieces.push(piece);
+        rest = &rest[position + length..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    pieces
+}
+
+// Binding strength of the printed form, a template that encloses all its operands is an atom
+pub(crate) fn template_precedence(template: &str, precedence: u8) -> u8 {
+    let pieces = pieces(template);
+    let is_operand = |piece: Option<&Piece>| matches!(piece, Some(Piece::Operand(..) | Piece::AllOperands));
+    if is_operand(pieces.first()) || is_operand(pieces.last()) { precedence } else { ATOM }
+}
+
+type Setting<'a> = (usize, &'a str, &'a str);
+
+impl Operators {
+    // Reads operators from a config file with one section per operator:
+    //
+    //     [hypot]
+    //     arity = 2
+    //     rpn = dup * swap dup * + sqrt
+    //     infix = hypot($0, $1)
+    //     latex = \operatorname{hypot}\left($0, $1\right)
+    //
+    // 'arity' and 'rpn' are required, without templates the operator is written like a function.
+    // 'precedence' (sum, product, power or atom) and 'associativity = right' are for templates like
+    // '$0 # $1'. Returns the names of the new operators, nothing is added if the text has an error
+    pub fn load(&mut self, text: &str) -> Result<Vec<String>, CalcError> {
+        // Line of the section, the name and the line, key and value of every setting
+        let mut sections: Vec<(usize, String, Vec<Setting>)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let invalid = |message: String| CalcError::InvalidConfig { line: index + 1, message };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                sections.push((index + 1, name.trim().to_owned(), Vec::new()));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid(format!("expected '[name]' or 'key = value', got '{}'", line)));
+            };
+            match sections.last_mut() {
+                Some((_, _, entries)) => entries.push((index + 1, key.trim(), value.trim())),
+                None => return Err(invalid(format!("'{}' is outside of an operator section", key.trim()))),
+            }
+        }
+
+        let mut added: Vec<(String, Operator)> = Vec::new();
+        for (line, name, entries) in sections {
+            let invalid = |line: usize, message: String| CalcError::InvalidConfig { line, message };
+            let taken = calculator::is_known_word(&name, self) || added.iter().any(|(other, _)| *other == name);
+            if !calculator::is_valid_name(&name) || taken {
+                return Err(invalid(line, format!("'{}' cannot be the name of an operator", name)));
+            }
+            let mut operator = Operator {
+                arity: Arity::Fixed(0),
+                evaluation: Evaluation::Rpn(Vec::new()),
+                // The tokens of the body decide which values they take
+                accepts: Accepts::ALL,
+                infix: String::new(),
+                latex: String::new(),
+                precedence: ATOM,
+                right_associative: false,
+                derivative: None,
+            };
+            for (line, key, value) in entries {
+                match key {
+                    "arity" => match value.parse::<usize>() {
+                        Ok(arity) if arity > 0 => operator.arity = Arity::Fixed(arity),
+                        _ => return Err(invalid(line, format!("'arity' has to be a positive number, got '{}'", value))),
+                    },
+                    "rpn" => {
+                        let body = calculator::group_literals(value).map_err(|error| invalid(line, error.to_string()))?;
+                        let known = |word: &String| {
+                            calculator::is_known_word(word, self) || added.iter().any(|(other, _)| other == word)
+                        };
+                        if let Some(word) = body.iter().find(|word| !known(word)) {
+                            return Err(invalid(line, format!("unknown word '{}' in 'rpn'", word)));
+                        }
+                        operator.evaluation = Evaluation::Rpn(body);
+                    }
+                    "infix" => operator.infix = value.to_owned(),
+                    "latex" => operator.latex = value.to_owned(),
+                    "precedence" => {
+                        operator.precedence = match value {
+                            "sum" => SUM,
+                            "product" => PRODUCT,
+                            "power" => POWER,
+                            "atom" => ATOM,
+                            _ => return Err(invalid(line, format!("unknown precedence '{}'", value))),
+                        }
+                    }
+                    "associativity" if value == "left" || value == "right" => {
+                        operator.right_associative = value == "right";
+                    }
+                    _ => return Err(invalid(line, format!("unknown setting '{} = {}'", key, value))),
+                }
+            }
+
+            let Arity::Fixed(arity) = operator.arity else { unreachable!() };
+            if arity == 0 {
+                return Err(invalid(line, format!("'{}' needs an 'arity'", name)));
+            }
+            if matches!(&operator.evaluation, Evaluation::Rpn(body) if body.is_empty()) {
+                return Err(invalid(line, format!("'{}' needs an 'rpn' body", name)));
+            }
+            let (infix, latex) = call_templates(arity, &name, &format!(r"\operatorname{{{}}}", name));
+            for (template, default) in [(&mut operator.infix, infix), (&mut operator.latex, latex)] {
+                if template.is_empty() {
+                    *template = default;
+                }
+                let outside = |piece: &Piece| matches!(piece, Piece::Operand(index, _) if *index >= arity);
+                if pieces(template).iter().any(outside) {
+                    let message = format!("the template '{}' has more than {} operand(s)", template, arity);
+                    return Err(invalid(line, message));
+                }
+            }
+            added.push((name, operator));
+        }
+
+        let names = added.iter().map(|(name, _)| name.clone()).collect();
+        self.operators.extend(added);
+        Ok(names)
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces() {
+        assert_eq!(pieces("$0 + $1"), [Piece::Operand(0, false), Piece::Text(" + "), Piece::Operand(1, false)]);
+        let (numerator, denominator) = (Piece::Operand(0, false), Piece::Operand(1, false));
+        assert_eq!(
+            pieces(r"\frac{$0}{$1}"),
+            [Piece::Text(r"\frac{"), numerator, Piece::Text("}{"), denominator, Piece::Text("}")]
+        );
+        assert_eq!(pieces("$(0)^{T}"), [Piece::Operand(0, true), Piece::Text("^{T}")]);
+        assert_eq!(pieces("mean($*)"), [Piece::Text("mean("), Piece::AllOperands, Piece::Text(")")]);
+        assert_eq!(template_precedence("$0 / $1", PRODUCT), PRODUCT);
+        assert_eq!(template_precedence(r"\frac{$0}{$1}", PRODUCT), ATOM);
+    }
+
+    #[test]
+    fn test_registry() {
+        let operators = Operators::default();
+        assert!(operators.is_operator("+") && operators.is_operator("linreg") && operators.is_operator("dup"));
+        assert!(!operators.is_operator("slope") && !operators.is_operator("pi") && !operators.is_operator("foo"));
+        assert!(operators.is_word("pi") && operators.is_word("++") && !operators.is_word("slope"));
+        assert!(operators.written("dup").is_none() && operators.written("slope").is_some());
+        assert_eq!(operators.function_call("log10"), Some(("log".to_owned(), 1)));
+        assert_eq!(operators.function_call("atan2"), Some(("atan2".to_owned(), 2)));
+        assert_eq!(operators.function_call("+"), None);
+        assert_eq!(operators.function_call("mean"), None);
+        assert_eq!(operators.function_call("dup"), None);
+        assert_eq!(operators.infix_operator("mod"), Some(("mod".to_owned(), 2)));
+        assert_eq!(operators.infix_operator("!"), Some(("!".to_owned(), 1)));
+        assert_eq!(operators.infix_operator("sqrt"), None);
+        assert!(operators.names().any(|name| name == "sqrt"));
+        let accepts = |name: &str| operators.get(name).unwrap().accepts;
+        assert!(accepts("dot").arrays && !accepts("dot").quantities && !accepts("sin").arrays);
+        assert!(accepts("+").quantities && accepts("diff").expressions && !accepts("mean").complex);
+    }
+
+    #[test]
+    fn test_load_operators() {
+        let mut operators = Operators::default();
+        let config = "# geometry\n[hypot]\narity = 2\nrpn = dup * swap dup * + sqrt\n\n\
+                      [circle]\narity = 1\nrpn = dup * pi *\nlatex = \\pi $(0)^{2}\n";
+        assert_eq!(operators.load(config), Ok(vec!["hypot".to_owned(), "circle".to_owned()]));
+        let hypot = operators.get("hypot").unwrap();
+        assert_eq!(hypot.arity, Arity::Fixed(2));
+        assert_eq!(hypot.infix, "hypot($0, $1)");
+        assert_eq!(hypot.latex, r"\operatorname{hypot}\left($0, $1\right)");
+        assert_eq!(operators.get("circle").unwrap().latex, r"\pi $(0)^{2}");
+        assert_eq!(operators.function_call("circle"), Some(("circle".to_owned(), 1)));
+        assert_eq!(hypot.accepts, Accepts::ALL);
+        // A name can only be taken once
+        assert!(operators.load(config).is_err());
+        // Other registries do not see them
+        assert!(Operators::default().get("hypot").is_none() && Operators::builtin().get("hypot").is_none());
+    }
+
+    #[test]
+    fn test_load_operator_errors() {
+        let mut operators = Operators::default();
+        let mut error = |text: &str| match operators.load(text) {
+            Err(CalcError::InvalidConfig { line, message }) => (line, message),
+            result => panic!("expected an error for '{}', got {:?}", text, result),
+        };
+        assert_eq!(error("arity = 1"), (1, "'arity' is outside of an operator section".to_owned()));
+        assert_eq!(error("[sqrt]\narity = 1\nrpn = 1 +"), (1, "'sqrt' cannot be the name of an operator".to_owned()));
+        assert_eq!(error("[dup]\narity = 1\nrpn = 1 +"), (1, "'dup' cannot be the name of an operator".to_owned()));
+        assert_eq!(error("[a b]\narity = 1\nrpn = 1 +").0, 1);
+        let arity = "'arity' has to be a positive number, got '0'".to_owned();
+        assert_eq!(error("[f]\narity = 0\nrpn = 1 +"), (2, arity));
+        assert_eq!(error("[f]\narity = 1\nrpn = 1 foo"), (3, "unknown word 'foo' in 'rpn'".to_owned()));
+        assert_eq!(error("[f]\nrpn = 1 +"), (1, "'f' needs an 'arity'".to_owned()));
+        assert_eq!(error("[f]\narity = 1"), (1, "'f' needs an 'rpn' body".to_owned()));
+        let setting = "unknown setting 'color = red'".to_owned();
+        assert_eq!(error("[f]\narity = 1\nrpn = 1 +\ncolor = red"), (4, setting));
+        assert_eq!(error("[f]\narity = 1\nrpn = 1 +\ninfix = f($1)").0, 1);
+        assert_eq!(error("[f]\nplain line").0, 2);
+        // The operators before an error are not added either
+        assert_eq!(error("[twice]\narity = 1\nrpn = 2 *\n[thrice]\narity = x").0, 5);
+        assert!(operators.get("twice").is_none());
+    }
+}
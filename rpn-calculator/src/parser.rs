@@ -0,0 +1,348 @@
+// Converts infix expressions like "(5 + 3) * sqrt(16)" to RPN tokens with the shunting-yard algorithm
+use crate::error::CalcError;
+use crate::format::parse_integer_literal;
+use crate::matrix;
+use crate::operators::{Operators, NEGATION};
+
+// 'i' is the imaginary unit and 'x' the variable of symbolic expressions, so neither can be used as a
+// stored variable in infix expressions
+const CONSTANTS: &[&str] = &["pi", "e", "i", "x"];
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(String),
+    Name(String),
+    // Symbol of an operator that is written between or after its operands
+    Operator(String),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+// Entries waiting on the operator stack
+enum Pending {
+    Operator(String),
+    // Operator of a function call like 'log10(x)' and its number of arguments
+    Function(String, usize),
+    LeftParen,
+}
+
+fn invalid(message: impl Into<String>) -> CalcError {
+    CalcError::InvalidExpression(message.into())
+}
+
+fn tokenize(expression: &str, operators: &Operators) -> Result<Vec<Token>, CalcError> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '0' && matches!(chars.get(i + 1), Some('x' | 'o' | 'b')) {
+            // Integers in another base like 0xff, the digits are checked by the calculator
+            let start = i;
+            i += 2;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Scientific notation like 2.5e-3, a lone 'e' after a number is the constant
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(|d| d.is_ascii_digit()) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            // Imaginary numbers like 4i
+            let unit = chars.get(i) == Some(&'i');
+            if unit && !chars.get(i + 1).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c == '[' {
+            // Vector and matrix literals are read up to their closing bracket and become one number
+            let start = i;
+            let mut depth = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                i += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let canonical = matrix::canonical(&literal).ok_or(CalcError::UnknownToken(literal))?;
+            tokens.push(Token::Number(canonical));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            let is_operator = operators.infix_operator(&name).is_some();
+            tokens.push(if is_operator { Token::Operator(name) } else { Token::Name(name) });
+        } else {
+            let token = match c {
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                ',' => Token::Comma,
+                _ => {
+                    // The longest symbol of an operator, so a '**' from the config file is not read as two '*'
+                    let end = (i..chars.len()).find(|&j| !is_symbol(chars[j])).unwrap_or(chars.len());
+                    let symbol = (i + 1..=end)
+                        .rev()
+                        .map(|j| chars[i..j].iter().collect::<String>())
+                        .find(|symbol| operators.infix_operator(symbol).is_some());
+                    let symbol = symbol.ok_or_else(|| CalcError::UnknownToken(c.to_string()))?;
+                    i += symbol.chars().count();
+                    tokens.push(Token::Operator(symbol));
+                    continue;
+                }
+            };
+            tokens.push(token);
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+// Characters of operator symbols like '+' or '%'
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace() && !"()[],._".contains(c)
+}
+
+// Numbers that can take a sign: decimal, imaginary and integers in another base
+fn is_literal(token: &str) -> bool {
+    token.parse::<f64>().is_ok() || token.ends_with('i') || parse_integer_literal(token).is_some()
+}
+
+// Moves an operator to the output, a negated number becomes a negative number
+fn emit(output: &mut Vec<String>, operator: String) {
+    if operator != "neg" {
+        output.push(operator);
+        return;
+    }
+    match output.last_mut() {
+        Some(last) if is_literal(last) && !last.starts_with('-') => last.insert(0, '-'),
+        _ => {
+            output.push("-1".to_owned());
+            output.push("*".to_owned());
+        }
+    }
+}
+
+// RPN tokens of an infix expression, variables are recalled with '$name'
+pub fn infix_to_rpn(expression: &str) -> Result<Vec<String>, CalcError> {
+    infix_to_rpn_with(expression, Operators::builtin())
+}
+
+// Like infix_to_rpn, the operators and functions are looked up in the operators of a calculator
+pub(crate) fn infix_to_rpn_with(expression: &str, operators: &Operators) -> Result<Vec<String>, CalcError> {
+    // Binding strength and associativity, 'neg' is the unary minus and the only operator outside the registry
+    let precedence = |operator: &str| {
+        let entry = operators.get(operator);
+        entry.map_or((NEGATION, true), |entry| (entry.precedence, entry.right_associative))
+    };
+    let mut output = Vec::new();
+    let mut pending: Vec<Pending> = Vec::new();
+    // Number of arguments seen so far in every open parenthesis
+    let mut argument_counts: Vec<usize> = Vec::new();
+    let mut expect_operand = true;
+
+    let mut tokens = tokenize(expression, operators)?.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Number(_) | Token::Name(_) if !expect_operand => {
+                return Err(invalid("missing operator between two operands"));
+            }
+            Token::Number(number) => {
+                output.push(number);
+                expect_operand = false;
+            }
+            Token::Name(name) if CONSTANTS.contains(&name.as_str()) => {
+                output.push(name);
+                expect_operand = false;
+            }
+            Token::Name(name) if tokens.peek() == Some(&Token::LeftParen) => {
+                match operators.function_call(&name) {
+                    Some((operator, arity)) => pending.push(Pending::Function(operator, arity)),
+                    None => return Err(CalcError::UnknownToken(name)),
+                }
+            }
+            Token::Name(name) if operators.function_call(&name).is_some() => {
+                return Err(invalid(format!("'{}' needs its arguments in parentheses", name)));
+            }
+            Token::Name(name) => {
+                output.push(format!("${}", name));
+                expect_operand = false;
+            }
+            Token::LeftParen if !expect_operand => {
+                return Err(invalid("missing operator before '('"));
+            }
+            Token::LeftParen => {
+                pending.push(Pending::LeftParen);
+                argument_counts.push(1);
+            }
+            Token::RightParen | Token::Comma if expect_operand => {
+                return Err(invalid("missing operand"));
+            }
+            Token::Comma => {
+                while let Some(Pending::Operator(_)) = pending.last() {
+                    let Some(Pending::Operator(operator)) = pending.pop() else { unreachable!() };
+                    emit(&mut output, operator);
+                }
+                match argument_counts.last_mut() {
+                    Some(count) => *count += 1,
+                    None => return Err(invalid("',' outside of a function call")),
+                }
+                expect_operand = true;
+            }
+            Token::RightParen => {
+                loop {
+                    match pending.pop() {
+                        Some(Pending::Operator(operator)) => emit(&mut output, operator),
+                        Some(Pending::LeftParen) => break,
+                        _ => return Err(invalid("missing '('")),
+                    }
+                }
+                let count = argument_counts.pop().unwrap();
+                if let Some(Pending::Function(..)) = pending.last() {
+                    let Some(Pending::Function(name, arity)) = pending.pop() else { unreachable!() };
+                    if count != arity {
+                        return Err(invalid(format!("'{}' takes {} argument(s), got {}", name, arity, count)));
+                    }
+                    output.push(name);
+                } else if count != 1 {
+                    return Err(invalid("',' outside of a function call"));
+                }
+            }
+            // Unary signs, a plus sign changes nothing
+            Token::Operator(symbol) if expect_operand && symbol == "-" => {
+                pending.push(Pending::Operator("neg".to_owned()));
+            }
+            Token::Operator(symbol) if expect_operand && symbol == "+" => {}
+            Token::Operator(symbol) if expect_operand => {
+                return Err(invalid(format!("missing operand before '{}'", symbol)));
+            }
+            Token::Operator(symbol) => {
+                let Some((operator, arity)) = operators.infix_operator(&symbol) else {
+                    return Err(CalcError::UnknownToken(symbol));
+                };
+                let (strength, right_associative) = precedence(&operator);
+                while let Some(Pending::Operator(top)) = pending.last() {
+                    let (top_strength, _) = precedence(top);
+                    if top_strength < strength || (top_strength == strength && right_associative) {
+                        break;
+                    }
+                    let Some(Pending::Operator(top)) = pending.pop() else { unreachable!() };
+                    emit(&mut output, top);
+                }
+                // A postfix operator like the factorial has its operand already, so it applies right away
+                if arity == 1 {
+                    output.push(operator);
+                } else {
+                    pending.push(Pending::Operator(operator));
+                    expect_operand = true;
+                }
+            }
+        }
+    }
+
+    if expect_operand {
+        return Err(invalid("incomplete expression"));
+    }
+    while let Some(entry) = pending.pop() {
+        match entry {
+            Pending::Operator(operator) => emit(&mut output, operator),
+            _ => return Err(invalid("missing ')'")),
+        }
+    }
+    Ok(output)
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpn(expression: &str) -> String {
+        infix_to_rpn(expression).unwrap().join(" ")
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(rpn("(5 + 3) * sqrt(16)"), "5 3 + 16 sqrt *");
+        assert_eq!(rpn("1 + 2 * 3 - 4"), "1 2 3 * + 4 -");
+        assert_eq!(rpn("8 / 4 / 2"), "8 4 / 2 /");
+        assert_eq!(rpn("2 ^ 3 ^ 2"), "2 3 2 ^ ^");
+        assert_eq!(rpn("7 mod 3 * 2"), "7 3 mod 2 *");
+        assert_eq!(rpn("3! + 2.5e-3"), "3 ! 2.5e-3 +");
+    }
+
+    #[test]
+    fn test_functions_and_names() {
+        assert_eq!(rpn("atan2(1, 2 + y) * pi"), "1 2 $y + atan2 pi *");
+        assert_eq!(rpn("cos(x) * (pi / 180) + log10(x)"), "x cos pi 180 / * x log +");
+        assert_eq!(rpn("max(min(1, 2), e)"), "1 2 min e max");
+        assert_eq!(rpn("2e3 * e"), "2e3 e *");
+        assert_eq!(rpn("3 - 4i * -i + conj(2.5i)"), "3 4i -i * - 2.5i conj +");
+        assert_eq!(rpn("cplx(1, 2) * 2"), "1 2 cplx 2 *");
+        assert_eq!(rpn("det([[1 2][3 4]]) * -[1, 2]"), "[[1,2],[3,4]] det [1,2] -1 * *");
+        assert_eq!(rpn("xor(0xff, shl(0b1, 4)) - -0o17"), "0xff 0b1 4 shl xor -0o17 -");
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        assert_eq!(rpn("-3 + 4"), "-3 4 +");
+        assert_eq!(rpn("-2 ^ 2"), "2 2 ^ -1 *");
+        assert_eq!(rpn("2 * -(1 + 1)"), "2 1 1 + -1 * *");
+        assert_eq!(rpn("2 ^ -1"), "2 -1 ^");
+        assert_eq!(rpn("+4 - -4"), "4 -4 -");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(infix_to_rpn("(1 + 2"), Err(invalid("missing ')'")));
+        assert_eq!(infix_to_rpn("1 + 2)"), Err(invalid("missing '('")));
+        assert_eq!(infix_to_rpn("1 +"), Err(invalid("incomplete expression")));
+        assert_eq!(infix_to_rpn("1 2"), Err(invalid("missing operator between two operands")));
+        assert_eq!(infix_to_rpn("* 2"), Err(invalid("missing operand before '*'")));
+        assert_eq!(infix_to_rpn("max(1)"), Err(invalid("'max' takes 2 argument(s), got 1")));
+        assert_eq!(infix_to_rpn("(1, 2)"), Err(invalid("',' outside of a function call")));
+        assert_eq!(infix_to_rpn("sqrt 4"), Err(invalid("'sqrt' needs its arguments in parentheses")));
+        assert_eq!(infix_to_rpn("foo(1)"), Err(CalcError::UnknownToken("foo".to_owned())));
+        assert_eq!(infix_to_rpn("1 & 2"), Err(CalcError::UnknownToken("&".to_owned())));
+        assert_eq!(infix_to_rpn("[1 2"), Err(CalcError::UnknownToken("[1 2".to_owned())));
+    }
+
+    #[test]
+    fn test_config_operators() {
+        let mut operators = Operators::default();
+        let config = "[pct]\narity = 2\nrpn = * 100 /\ninfix = $0 % $1\nprecedence = product\n\
+                      [avg]\narity = 2\nrpn = + 2 /\ninfix = $0 avg $1\nprecedence = sum\n\
+                      [pow]\narity = 2\nrpn = ^\ninfix = $0 ** $1\nprecedence = power\nassociativity = right\n\
+                      [square]\narity = 1\nrpn = dup *\ninfix = $0!!\n";
+        operators.load(config).unwrap();
+        let rpn = |expression: &str| infix_to_rpn_with(expression, &operators).unwrap().join(" ");
+        assert_eq!(rpn("2 + 3 % 50"), "2 3 50 pct +");
+        assert_eq!(rpn("1 avg 3 * 2"), "1 3 2 * avg");
+        assert_eq!(rpn("2 ** 3 ** -2 * 2"), "2 3 -2 pow pow 2 *");
+        assert_eq!(rpn("3!! + 2 * 3!"), "3 square 2 3 ! * +");
+        // Other registries do not know them
+        assert_eq!(infix_to_rpn("3 % 50"), Err(CalcError::UnknownToken("%".to_owned())));
+    }
+}
@@ -0,0 +1,266 @@
+// Charts of a function of x, drawn with braille characters for the terminal or as an SVG image
+use std::fmt::Write;
+
+// Space around the chart area of the SVG image for the title, the tick labels and the axis names
+const SVG_LEFT: f64 = 70.0;
+const SVG_RIGHT: f64 = 20.0;
+const SVG_TOP: f64 = 40.0;
+const SVG_BOTTOM: f64 = 50.0;
+
+// Number of tick intervals the axes of the SVG image aim for
+const TICK_INTERVALS: f64 = 6.0;
+
+// Sampled points of a function, y is NaN where the function has no real value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plot {
+    title: String,
+    points: Vec<(f64, f64)>,
+}
+
+impl Plot {
+    // The points are sorted by x and at least one of them has a finite y
+    pub fn new(title: &str, points: Vec<(f64, f64)>) -> Plot {
+        Plot { title: title.to_owned(), points }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    fn x_range(&self) -> (f64, f64) {
+        (self.points[0].0, self.points[self.points.len() - 1].0)
+    }
+
+    // Smallest and largest y, a constant function gets some room above and below
+    fn y_range(&self) -> (f64, f64) {
+        let finite = self.points.iter().map(|&(_, y)| y).filter(|y| y.is_finite());
+        let (low, high) = finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), y| (low.min(y), high.max(y)));
+        if low < high { (low, high) } else { (low - 1.0, high + 1.0) }
+    }
+
+    // Runs of neighbouring points that all have a value, the curve has a gap between them
+    fn segments(&self) -> impl Iterator<Item = &[(f64, f64)]> {
+        self.points.split(|(_, y)| !y.is_finite()).filter(|segment| !segment.is_empty())
+    }
+
+    // Every character holds 2 x 4 dots. The y range is written next to the top and bottom row,
+    // the x range below the chart
+    pub fn to_braille(&self, columns: usize, rows: usize) -> String {
+        let (width, height) = (columns * 2, rows * 4);
+        let (x0, x1) = self.x_range();
+        let (y0, y1) = self.y_range();
+        let pixel = |(x, y): (f64, f64)| {
+            let column = ((x - x0) / (x1 - x0) * (width - 1) as f64).round();
+            let row = ((y1 - y) / (y1 - y0) * (height - 1) as f64).round();
+            (column as i64, row as i64)
+        };
+
+        let mut dots = vec![vec![false; width]; height];
+        let mut set = |(column, row): (i64, i64)| {
+            if (0..width as i64).contains(&column) && (0..height as i64).contains(&row) {
+                dots[row as usize][column as usize] = true;
+            }
+        };
+        for segment in self.segments() {
+            set(pixel(segment[0]));
+            // Neighbouring samples are joined with a straight line of dots
+            for pair in segment.windows(2) {
+                let ((c0, r0), (c1, r1)) = (pixel(pair[0]), pixel(pair[1]));
+                let steps = (c1 - c0).abs().max((r1 - r0).abs()).max(1);
+                for step in 1..=steps {
+                    let t = step as f64 / steps as f64;
+                    let between = |a: i64, b: i64| (a as f64 + t * (b - a) as f64).round() as i64;
+                    set((between(c0, c1), between(r0, r1)));
+                }
+            }
+        }
+
+        let (top, bottom) = (label(y1), label(y0));
+        let margin = top.chars().count().max(bottom.chars().count());
+        let mut lines = vec![format!("y = {}", self.title)];
+        for row in 0..rows {
+            let line: String = (0..columns).map(|column| braille_cell(&dots, column * 2, row * 4)).collect();
+            let (text, tick) = match row {
+                0 => (top.as_str(), '┤'),
+                _ if row == rows - 1 => (bottom.as_str(), '┤'),
+                _ => ("", '│'),
+            };
+            lines.push(format!("{:>margin$} {}{}", text, tick, line));
+        }
+        lines.push(format!("{:margin$} └{}", "", "─".repeat(columns)));
+        let (left, right) = (label(x0), label(x1));
+        let gap = (columns + 1).saturating_sub(left.chars().count() + right.chars().count()).max(1);
+        lines.push(format!("{:margin$} {}{}{}", "", left, " ".repeat(gap), right));
+        lines.join("\n")
+    }
+
+    // Chart with a frame, grid lines, ticks with their labels, the axes through zero and the curve
+    pub fn to_svg(&self, width: u32, height: u32) -> String {
+        let (width, height) = (f64::from(width), f64::from(height));
+        let (chart_width, chart_height) = (width - SVG_LEFT - SVG_RIGHT, height - SVG_TOP - SVG_BOTTOM);
+        let (x0, x1) = self.x_range();
+        let (y0, y1) = self.y_range();
+        let sx = |x: f64| SVG_LEFT + (x - x0) / (x1 - x0) * chart_width;
+        let sy = |y: f64| SVG_TOP + (y1 - y) / (y1 - y0) * chart_height;
+        let (right, bottom) = (SVG_LEFT + chart_width, SVG_TOP + chart_height);
+
+        let mut svg = String::new();
+        let _ = write!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" "#);
+        let _ = writeln!(svg, r#"viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12">"#);
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="24" text-anchor="middle" font-size="16">y = {}</text>"#,
+            width / 2.0,
+            escape(&self.title)
+        );
+        for tick in ticks(x0, x1) {
+            let x = sx(tick);
+            let _ = writeln!(svg, r##"<line x1="{x:.1}" y1="{SVG_TOP}" x2="{x:.1}" y2="{bottom}" stroke="#e0e0e0"/>"##);
+            let outside = bottom + 5.0;
+            let _ = writeln!(svg, r#"<line x1="{x:.1}" y1="{bottom}" x2="{x:.1}" y2="{outside}" stroke="black"/>"#);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{x:.1}" y="{}" text-anchor="middle">{}</text>"#,
+                bottom + 18.0,
+                label(tick)
+            );
+        }
+        for tick in ticks(y0, y1) {
+            let y = sy(tick);
+            let _ = writeln!(svg, r##"<line x1="{SVG_LEFT}" y1="{y:.1}" x2="{right}" y2="{y:.1}" stroke="#e0e0e0"/>"##);
+            let outside = SVG_LEFT - 5.0;
+            let _ = writeln!(svg, r#"<line x1="{outside}" y1="{y:.1}" x2="{SVG_LEFT}" y2="{y:.1}" stroke="black"/>"#);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#,
+                SVG_LEFT - 8.0,
+                y + 4.0,
+                label(tick)
+            );
+        }
+        if x0 < 0.0 && 0.0 < x1 {
+            let x = sx(0.0);
+            let _ = writeln!(svg, r##"<line x1="{x:.1}" y1="{SVG_TOP}" x2="{x:.1}" y2="{bottom}" stroke="#808080"/>"##);
+        }
+        if y0 < 0.0 && 0.0 < y1 {
+            let y = sy(0.0);
+            let _ = writeln!(svg, r##"<line x1="{SVG_LEFT}" y1="{y:.1}" x2="{right}" y2="{y:.1}" stroke="#808080"/>"##);
+        }
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+            SVG_LEFT, SVG_TOP, chart_width, chart_height
+        );
+        for segment in self.segments() {
+            let points: Vec<String> = segment.iter().map(|&(x, y)| format!("{:.1},{:.1}", sx(x), sy(y))).collect();
+            let _ = writeln!(
+                svg,
+                r##"<polyline points="{}" fill="none" stroke="#1f77b4" stroke-width="2"/>"##,
+                points.join(" ")
+            );
+        }
+        let (middle, baseline) = (SVG_LEFT + chart_width / 2.0, height - 8.0);
+        let _ = writeln!(svg, r#"<text x="{middle}" y="{baseline}" text-anchor="middle">x</text>"#);
+        let _ = writeln!(svg, r#"<text x="16" y="{}" text-anchor="middle">y</text>"#, SVG_TOP + chart_height / 2.0);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+// The dots of one braille character, its top left dot is at the given column and row
+fn braille_cell(dots: &[Vec<bool>], column: usize, row: usize) -> char {
+    // Bit of every dot by row and column, the bottom row was added to the braille codes last
+    const BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let mut code = 0x2800;
+    for (dy, bits) in BITS.iter().enumerate() {
+        for (dx, bit) in bits.iter().enumerate() {
+            if dots[row + dy][column + dx] {
+                code |= bit;
+            }
+        }
+    }
+    char::from_u32(code).unwrap()
+}
+
+// Ticks at multiples of 1, 2 or 5 times a power of ten
+fn ticks(low: f64, high: f64) -> Vec<f64> {
+    let raw = (high - low) / TICK_INTERVALS;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0].into_iter().map(|factor| factor * magnitude).find(|step| *step >= raw).unwrap();
+    let (first, last) = ((low / step).ceil() as i64, (high / step).floor() as i64);
+    (first..=last).map(|index| index as f64 * step).collect()
+}
+
+// Short number for the axes, without the rounding noise of the tick positions
+fn label(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude < 1e-9 {
+        "0".to_owned()
+    } else if !(1e-3..1e5).contains(&magnitude) {
+        format!("{:.1e}", value)
+    } else {
+        let text = format!("{:.4}", value);
+        text.trim_end_matches('0').trim_end_matches('.').to_owned()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line() -> Plot {
+        Plot::new("x", (0..=8).map(|i| (i as f64 - 4.0, i as f64 - 4.0)).collect())
+    }
+
+    #[test]
+    fn test_braille() {
+        let text = line().to_braille(4, 2);
+        let lines: Vec<&str> = text.lines().collect();
+        let expected = ["y = x", " 4 ┤⠀⠀⡠⠊", "-4 ┤⡠⠊⠁⠀", "   └────", "   -4  4"];
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let points = vec![(0.0, 0.0), (1.0, f64::NAN), (2.0, 1.0), (3.0, 1.0)];
+        let plot = Plot::new("f", points);
+        assert_eq!(plot.segments().map(<[(f64, f64)]>::len).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(plot.y_range(), (0.0, 1.0));
+        assert_eq!(Plot::new("c", vec![(0.0, 2.0), (1.0, 2.0)]).y_range(), (1.0, 3.0));
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = Plot::new("x < 1 & y", line().points().to_vec()).to_svg(640, 400);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">y = x &lt; 1 &amp; y</text>"));
+        assert_eq!(svg.matches("<polyline ").count(), 1);
+        // Ticks every 2 on both axes, labelled below and to the left
+        assert!(svg.contains(r#"text-anchor="middle">-4</text>"#));
+        assert!(svg.contains(r#"text-anchor="end">2</text>"#));
+        assert_eq!(svg.matches(r##"stroke="#808080""##).count(), 2);
+    }
+
+    #[test]
+    fn test_ticks_and_labels() {
+        assert_eq!(ticks(-4.0, 4.0), [-4.0, -2.0, 0.0, 2.0, 4.0]);
+        assert_eq!(ticks(0.0, 1.0).len(), 6);
+        assert_eq!(ticks(0.5, 30.0), [5.0, 10.0, 15.0, 20.0, 25.0, 30.0]);
+        assert_eq!(label(0.30000000000000004), "0.3");
+        assert_eq!(label(-2.0), "-2");
+        assert_eq!(label(1e-12), "0");
+        assert_eq!(label(250000.0), "2.5e5");
+    }
+}
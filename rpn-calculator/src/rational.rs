@@ -0,0 +1,260 @@
+// Library for implementing the display
+use std::fmt;
+// Library for comparing rationals
+use std::cmp::Ordering;
+
+use crate::bigint::BigInt;
+
+// Exact fraction of two big integers, always reduced and with a positive denominator
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    // Reduced fraction, None if the denominator is zero
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let divisor = numerator.gcd(&denominator);
+        let (mut numerator, _) = numerator.div_rem(&divisor)?;
+        let (mut denominator, _) = denominator.div_rem(&divisor)?;
+        if denominator.is_negative() {
+            numerator = -numerator;
+            denominator = -denominator;
+        }
+        Some(Rational { numerator, denominator })
+    }
+
+    pub fn from_integer(value: BigInt) -> Self {
+        Rational { numerator: value, denominator: BigInt::one() }
+    }
+
+    // Exact value of a finite float, every finite float is a fraction with a power of two as denominator
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let mut scaled = value;
+        let mut exponent = 0;
+        while scaled.fract() != 0.0 {
+            scaled *= 2.0;
+            exponent += 1;
+        }
+        Rational::new(BigInt::from_f64(scaled)?, BigInt::one().shl(exponent))
+    }
+
+    // Parses integers ("42"), decimals ("-0.1"), scientific notation ("2.5e-3") and fractions ("1/3")
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some((numerator, denominator)) = text.split_once('/') {
+            return Rational::new(BigInt::parse(numerator)?, BigInt::parse(denominator)?);
+        }
+
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(index) => (&text[..index], text[index + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (integer_part, fraction_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if !fraction_part.bytes().all(|b| b.is_ascii_digit())
+            || integer_part.trim_start_matches(['+', '-']).is_empty() && fraction_part.is_empty()
+        {
+            return None;
+        }
+        // Giant exponents would need giant powers of ten
+        if exponent.unsigned_abs() > 100_000 {
+            return None;
+        }
+
+        // "-1.25e3" becomes -125 * 10^(3 - 2)
+        let digits = match integer_part {
+            "" | "+" | "-" => format!("{}0{}", integer_part, fraction_part),
+            _ => format!("{}{}", integer_part, fraction_part),
+        };
+        let numerator = BigInt::parse(&digits)?;
+        let exponent = exponent - fraction_part.len() as i64;
+        let power = BigInt::from(10u64).pow(exponent.unsigned_abs());
+        if exponent >= 0 {
+            Some(Rational::from_integer(&numerator * &power))
+        } else {
+            Rational::new(numerator, power)
+        }
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == BigInt::one()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.numerator.is_negative()
+    }
+
+    pub fn abs(&self) -> Self {
+        Rational { numerator: self.numerator.abs(), denominator: self.denominator.clone() }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        // Small fractions convert directly, large ones are scaled to 64 significant bits first
+        if self.numerator.bits() < 1000 && self.denominator.bits() < 1000 {
+            return self.numerator.to_f64() / self.denominator.to_f64();
+        }
+        let shift = self.numerator.bits() as i64 - self.denominator.bits() as i64 - 64;
+        let (quotient, _) = if shift > 0 {
+            self.numerator.div_rem(&self.denominator.shl(shift as u64)).unwrap()
+        } else {
+            self.numerator.shl((-shift) as u64).div_rem(&self.denominator).unwrap()
+        };
+        quotient.to_f64() * 2f64.powi(shift.clamp(-1100, 1100) as i32)
+    }
+
+    pub fn add(&self, other: &Rational) -> Rational {
+        let left = &self.numerator * &other.denominator;
+        let right = &other.numerator * &self.denominator;
+        Rational::new(&left + &right, &self.denominator * &other.denominator).unwrap()
+    }
+
+    pub fn sub(&self, other: &Rational) -> Rational {
+        let negated = Rational { numerator: -other.numerator.clone(), denominator: other.denominator.clone() };
+        self.add(&negated)
+    }
+
+    pub fn mul(&self, other: &Rational) -> Rational {
+        let numerator = &self.numerator * &other.numerator;
+        Rational::new(numerator, &self.denominator * &other.denominator).unwrap()
+    }
+
+    // None when dividing by zero
+    pub fn div(&self, other: &Rational) -> Option<Rational> {
+        Rational::new(&self.numerator * &other.denominator, &self.denominator * &other.numerator)
+    }
+
+    // Integer power, None for a negative power of zero
+    pub fn pow(&self, exponent: i64) -> Option<Rational> {
+        let numerator = self.numerator.pow(exponent.unsigned_abs());
+        let denominator = self.denominator.pow(exponent.unsigned_abs());
+        if exponent >= 0 {
+            Rational::new(numerator, denominator)
+        } else {
+            Rational::new(denominator, numerator)
+        }
+    }
+
+    // Largest integer not above the value
+    pub fn floor(&self) -> BigInt {
+        let (quotient, remainder) = self.numerator.div_rem(&self.denominator).unwrap();
+        if remainder.is_negative() { &quotient - &BigInt::one() } else { quotient }
+    }
+
+    // Smallest integer not below the value
+    pub fn ceil(&self) -> BigInt {
+        -Rational { numerator: -self.numerator.clone(), denominator: self.denominator.clone() }.floor()
+    }
+
+    // Nearest integer, halves are rounded away from zero like f64::round
+    pub fn round(&self) -> BigInt {
+        let half = Rational::new(BigInt::one(), BigInt::from(2u64)).unwrap();
+        if self.is_negative() {
+            -self.abs().add(&half).floor()
+        } else {
+            self.add(&half).floor()
+        }
+    }
+
+    // Exact square root if numerator and denominator are both perfect squares
+    pub fn exact_sqrt(&self) -> Option<Rational> {
+        Rational::new(self.numerator.exact_sqrt()?, self.denominator.exact_sqrt()?)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rational(text: &str) -> Rational {
+        Rational::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(rational("42").to_string(), "42");
+        assert_eq!(rational("-0.1").to_string(), "-1/10");
+        assert_eq!(rational(".5").to_string(), "1/2");
+        assert_eq!(rational("2.5e-3").to_string(), "1/400");
+        assert_eq!(rational("1.5E3").to_string(), "1500");
+        assert_eq!(rational("6/-4").to_string(), "-3/2");
+        assert_eq!(Rational::parse("1/0"), None);
+        assert_eq!(Rational::parse("1.2.3"), None);
+        assert_eq!(Rational::parse("."), None);
+        assert_eq!(Rational::parse("inf"), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(rational("0.1").add(&rational("0.2")), rational("0.3"));
+        assert_eq!(rational("1/3").sub(&rational("1/2")).to_string(), "-1/6");
+        assert_eq!(rational("2/3").mul(&rational("9/4")).to_string(), "3/2");
+        assert_eq!(rational("2/3").div(&rational("-4")).unwrap().to_string(), "-1/6");
+        assert_eq!(rational("2/3").div(&rational("0")), None);
+        assert_eq!(rational("-2/3").pow(-3).unwrap().to_string(), "-27/8");
+        assert_eq!(rational("0").pow(-1), None);
+        assert_eq!(rational("9/16").exact_sqrt(), Some(rational("3/4")));
+        assert_eq!(rational("2").exact_sqrt(), None);
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(rational("-7/2").floor(), BigInt::from(-4i64));
+        assert_eq!(rational("-7/2").ceil(), BigInt::from(-3i64));
+        assert_eq!(rational("-7/2").round(), BigInt::from(-4i64));
+        assert_eq!(rational("5/2").round(), BigInt::from(3i64));
+        assert_eq!(rational("4").floor(), BigInt::from(4i64));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(rational("1/4").to_f64(), 0.25);
+        assert_eq!(Rational::from_f64(0.375), Some(rational("3/8")));
+        assert_eq!(Rational::from_f64(f64::NAN), None);
+        let huge = Rational::from_integer(BigInt::from(10u64).pow(400));
+        assert_eq!(huge.div(&Rational::from_integer(BigInt::from(10u64).pow(399))).unwrap().to_f64(), 10.0);
+        assert_eq!(huge.to_f64(), f64::INFINITY);
+        assert!(rational("1/3") < rational("0.34"));
+    }
+}
@@ -0,0 +1,238 @@
+// Local HTTP service for the evaluator: POST /eval with a JSON body and get the result as JSON.
+// Every request runs on a fresh calculator with exactly the semantics of the command line
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use rpn_calculator::{CalcError, RPNCalculator};
+
+use crate::json::Json;
+
+// Larger request bodies are rejected without reading them
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+// A client that stops sending does not block the service for longer than this
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Answers requests one after the other, a failed connection only ends that connection. Every request
+// starts from a copy of the given empty calculator, so it has the same operators
+pub fn serve(listener: TcpListener, calc: RPNCalculator) -> ! {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(error) = handle_connection(stream, &calc) {
+                    eprintln!("Connection failed: {}", error);
+                }
+            }
+            Err(error) => eprintln!("Cannot accept connection: {}", error),
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, calc: &RPNCalculator) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else { continue };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().unwrap_or(usize::MAX),
+            // curl waits for this before it sends larger bodies
+            "expect" if value.trim().eq_ignore_ascii_case("100-continue") => {
+                stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            }
+            _ => {}
+        }
+    }
+
+    let (status, body) = if content_length > MAX_BODY_BYTES {
+        (413, error_body("PayloadTooLarge", &format!("request body is larger than {} bytes", MAX_BODY_BYTES)))
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        respond(method, target, &String::from_utf8_lossy(&body), calc.clone())
+    };
+    let allow = if status == 405 { "Allow: POST\r\n" } else { "" };
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        allow,
+        body
+    )?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Unprocessable Entity",
+    }
+}
+
+// Status code and JSON answer of a request. The body holds exactly one of
+// {"rpn": "5 3 +"}, {"tokens": ["5", "3", "+"]} or {"infix": "5 + 3"}
+fn respond(method: &str, target: &str, body: &str, mut calc: RPNCalculator) -> (u16, Json) {
+    let path = target.split('?').next().unwrap_or("");
+    if path != "/eval" {
+        return (404, error_body("NotFound", &format!("no endpoint '{}', use POST /eval", path)));
+    }
+    if method != "POST" {
+        return (405, error_body("MethodNotAllowed", "use POST /eval"));
+    }
+    let Some(request) = Json::parse(body) else {
+        return (400, error_body("BadRequest", "the request body is not valid JSON or nested too deeply"));
+    };
+
+    let result = match (request.get("rpn"), request.get("tokens"), request.get("infix")) {
+        (Some(Json::String(line)), None, None) => calc.evaluate_line(line),
+        (None, Some(Json::Array(tokens)), None) => match string_elements(tokens) {
+            // A token list means the same as the tokens separated by spaces
+            Some(tokens) => calc.evaluate_line(&tokens.join(" ")),
+            None => return (400, error_body("BadRequest", "'tokens' has to be a list of strings")),
+        },
+        (None, None, Some(Json::String(expression))) => calc.evaluate_infix(expression).map(|_| ()),
+        _ => {
+            let message = "expected exactly one of 'rpn' (a string), 'tokens' (a list) or 'infix' (a string)";
+            return (400, error_body("BadRequest", message));
+        }
+    };
+    match result {
+        Ok(()) => (200, evaluation_body(&calc)),
+        Err(error) => (422, calc_error_body(&error)),
+    }
+}
+
+fn string_elements(elements: &[Json]) -> Option<Vec<&str>> {
+    elements
+        .iter()
+        .map(|element| match element {
+            Json::String(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Values are strings in the display format of the calculator, since fractions and
+// complex numbers have no JSON number. 'result' is null unless exactly one value is left
+fn evaluation_body(calc: &RPNCalculator) -> Json {
+    let result = calc.get_result().map_or(Json::Null, |value| Json::from(calc.format_value(&value)));
+    let stack = calc.stack().iter().map(|value| Json::from(calc.format_value(value))).collect();
+    Json::object(vec![
+        ("result", result),
+        ("stack", Json::Array(stack)),
+        ("infix", Json::from(calc.infix())),
+        ("latex", Json::from(calc.latex())),
+    ])
+}
+
+fn calc_error_body(error: &CalcError) -> Json {
+    error_body(error.kind(), &error.to_string())
+}
+
+fn error_body(kind: &str, message: &str) -> Json {
+    Json::object(vec![("error", Json::object(vec![("kind", Json::from(kind)), ("message", Json::from(message))]))])
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn post(body: &str) -> (u16, String) {
+        let (status, json) = respond("POST", "/eval", body, RPNCalculator::new());
+        (status, json.to_string())
+    }
+
+    #[test]
+    fn test_evaluation() {
+        let expected = r#"{"result":"8","stack":["8"],"infix":"5 + 3","latex":"5 + 3"}"#;
+        assert_eq!(post(r#"{"rpn": "5 3 +"}"#), (200, expected.to_owned()));
+        assert_eq!(post(r#"{"tokens": ["5", "3", "+"]}"#), (200, expected.to_owned()));
+        assert_eq!(post(r#"{"infix": "5 + 3"}"#), (200, expected.to_owned()));
+
+        let (status, body) = post(r#"{"rpn": "exact 1 2 / [1 2] hex 255"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"result":null,"stack":["1/2","[0x1 0x2]","0xff"],"infix":"255","latex":"255"}"#);
+        assert_eq!(post(r#"{"tokens": []}"#).1, r#"{"result":null,"stack":[],"infix":"","latex":""}"#);
+
+        // Operators of a config file are only known to the calculator that loaded them
+        let mut calc = RPNCalculator::new();
+        calc.load_operators("[double]\narity = 1\nrpn = 2 *").unwrap();
+        let (status, body) = respond("POST", "/eval", r#"{"rpn": "3 double"}"#, calc);
+        assert_eq!(status, 200);
+        assert!(body.to_string().starts_with(r#"{"result":"6","stack":["6"],"infix":"double(3)","#));
+        assert_eq!(post(r#"{"rpn": "3 double"}"#).0, 422);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (status, body) = post(r#"{"rpn": "1 +"}"#);
+        assert_eq!(status, 422);
+        let message = "Stack underflow: '+' needs 2 value(s), but only 1 available";
+        assert_eq!(body, format!(r#"{{"error":{{"kind":"StackUnderflow","message":"{}"}}}}"#, message));
+        assert_eq!(post(r#"{"rpn": "1 foo"}"#).0, 422);
+        assert!(post("5 3 +").1.contains(r#""kind":"BadRequest""#));
+        assert_eq!(post(r#"{"tokens": [1, 2]}"#).0, 400);
+        assert_eq!(post(r#"{"rpn": "1", "infix": "1"}"#).0, 400);
+        assert_eq!(post(r#"{"rpn": 1}"#).0, 400);
+        let (status, body) = post(&"[".repeat(200_000));
+        assert_eq!(status, 400);
+        assert!(body.contains(r#""kind":"BadRequest""#));
+        assert_eq!(respond("GET", "/eval", "", RPNCalculator::new()).0, 405);
+        assert_eq!(respond("POST", "/", "", RPNCalculator::new()).0, 404);
+        assert_eq!(respond("POST", "/eval?pretty", r#"{"rpn": "1"}"#, RPNCalculator::new()).0, 200);
+    }
+
+    #[test]
+    fn test_serve_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let calc = RPNCalculator::new();
+            for _ in 0..2 {
+                handle_connection(listener.accept().unwrap().0, &calc).unwrap();
+            }
+        });
+
+        let request = |text: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(text.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let body = r#"{"rpn": "2 3 ^"}"#;
+        let response = request(&format!(
+            "POST /eval HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.ends_with(r#"{"result":"8","stack":["8"],"infix":"2 ^ 3","latex":"2^{3}"}"#));
+
+        let response = request("GET /eval HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("Allow: POST\r\n"));
+        server.join().unwrap();
+    }
+}
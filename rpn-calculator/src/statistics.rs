@@ -0,0 +1,135 @@
+// Statistics over a list of real values. The arithmetic goes through RPNCalculator::combine,
+// so the statistics of exact values stay exact unless a square root is irrational
+use crate::bigint::BigInt;
+use crate::calculator::RPNCalculator;
+use crate::error::CalcError;
+use crate::rational::Rational;
+use crate::value::Value;
+
+pub(crate) fn count(values: &[Value]) -> Value {
+    Value::Exact(Rational::from_integer(BigInt::from(values.len() as u64)))
+}
+
+fn sum(values: &[Value]) -> Result<Value, CalcError> {
+    let mut values = values.iter();
+    let first = values.next().unwrap().clone();
+    values.try_fold(first, |sum, value| RPNCalculator::combine("+", &sum, value))
+}
+
+pub(crate) fn mean(values: &[Value]) -> Result<Value, CalcError> {
+    RPNCalculator::combine("/", &sum(values)?, &count(values))
+}
+
+// Sample variance, the squared deviations are divided by n - 1
+pub(crate) fn variance(values: &[Value]) -> Result<Value, CalcError> {
+    let mean = mean(values)?;
+    let squares = values
+        .iter()
+        .map(|value| {
+            let deviation = RPNCalculator::combine("-", value, &mean)?;
+            RPNCalculator::combine("*", &deviation, &deviation)
+        })
+        .collect::<Result<Vec<Value>, CalcError>>()?;
+    RPNCalculator::combine("/", &sum(&squares)?, &count(&values[1..]))
+}
+
+fn sorted(values: &[Value]) -> Vec<Value> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.to_f64().total_cmp(&b.to_f64()));
+    sorted
+}
+
+// The middle value, or the mean of both middle values for an even count
+pub(crate) fn median(values: &[Value]) -> Result<Value, CalcError> {
+    let sorted = sorted(values);
+    let middle = sorted.len() / 2;
+    if !sorted.len().is_multiple_of(2) {
+        return Ok(sorted[middle].clone());
+    }
+    mean(&sorted[middle - 1..=middle])
+}
+
+pub(crate) fn standard_deviation(values: &[Value]) -> Result<Value, CalcError> {
+    match variance(values)? {
+        Value::Exact(x) => Ok(x.exact_sqrt().map(Value::Exact).unwrap_or(Value::Float(x.to_f64().sqrt()))),
+        variance => Ok(Value::Float(variance.to_f64().sqrt())),
+    }
+}
+
+// The smallest or largest value is kept as it is, exact or not
+pub(crate) fn minimum(values: &[Value]) -> Value {
+    sorted(values).remove(0)
+}
+
+pub(crate) fn maximum(values: &[Value]) -> Value {
+    sorted(values).pop().unwrap()
+}
+
+// 'linreg' takes x/y pairs, so an odd number of values is wrong no matter how many there are
+pub(crate) fn check_pairs(count: usize) -> Result<(), CalcError> {
+    if count.is_multiple_of(2) {
+        return Ok(());
+    }
+    Err(CalcError::DomainError(format!("'linreg' needs an even number of values (x/y pairs), got {}", count)))
+}
+
+// Least squares line y = intercept + slope * x through the pairs x1 y1 x2 y2 ..., returns (intercept, slope)
+pub(crate) fn linear_regression(values: &[Value]) -> Result<(Value, Value), CalcError> {
+    check_pairs(values.len())?;
+    let combine = RPNCalculator::combine;
+    let xs: Vec<Value> = values.iter().step_by(2).cloned().collect();
+    let ys: Vec<Value> = values.iter().skip(1).step_by(2).cloned().collect();
+    let products = xs.iter().zip(&ys).map(|(x, y)| combine("*", x, y)).collect::<Result<Vec<Value>, _>>()?;
+    let squares = xs.iter().map(|x| combine("*", x, x)).collect::<Result<Vec<Value>, _>>()?;
+    let (n, sum_x, sum_y) = (count(&xs), sum(&xs)?, sum(&ys)?);
+
+    // slope = (n Σxy - Σx Σy) / (n Σx² - (Σx)²)
+    let numerator = combine("-", &combine("*", &n, &sum(&products)?)?, &combine("*", &sum_x, &sum_y)?)?;
+    let denominator = combine("-", &combine("*", &n, &sum(&squares)?)?, &combine("*", &sum_x, &sum_x)?)?;
+    if denominator.to_f64() == 0.0 {
+        return Err(CalcError::DomainError("'linreg' needs at least two different x values".to_owned()));
+    }
+    let slope = combine("/", &numerator, &denominator)?;
+    let intercept = combine("/", &combine("-", &sum_y, &combine("*", &slope, &sum_x)?)?, &n)?;
+    Ok((intercept, slope))
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(text: &str) -> Value {
+        Value::Exact(Rational::parse(text).unwrap())
+    }
+
+    fn values(texts: &[&str]) -> Vec<Value> {
+        texts.iter().map(|text| exact(text)).collect()
+    }
+
+    #[test]
+    fn test_statistics() {
+        let data = values(&["2", "4", "4", "4", "5", "5", "7", "9"]);
+        assert_eq!(mean(&data), Ok(exact("5")));
+        assert_eq!(median(&data), Ok(exact("9/2")));
+        assert_eq!(variance(&data), Ok(exact("32/7")));
+        assert_eq!(count(&data), exact("8"));
+        assert_eq!(minimum(&data), exact("2"));
+        assert_eq!(maximum(&data), exact("9"));
+        assert_eq!(standard_deviation(&values(&["1", "3", "5"])), Ok(exact("2")));
+        assert_eq!(standard_deviation(&values(&["1", "3"])), Ok(Value::Float(2f64.sqrt())));
+        let floats = [Value::Float(3.0), Value::Float(1.0), Value::Float(2.0)];
+        assert_eq!(median(&floats), Ok(Value::Float(2.0)));
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let line = linear_regression(&values(&["1", "3", "2", "5", "3", "7"]));
+        assert_eq!(line, Ok((exact("1"), exact("2"))));
+        let line = linear_regression(&values(&["0", "1", "1", "2", "2", "2"]));
+        assert_eq!(line, Ok((exact("7/6"), exact("1/2"))));
+        assert!(linear_regression(&values(&["1", "2", "1", "3"])).is_err());
+        assert!(linear_regression(&values(&["1", "2", "3"])).is_err());
+    }
+}
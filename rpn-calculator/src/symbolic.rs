@@ -0,0 +1,397 @@
+// Expressions of the symbolic variable x: derivatives and algebraic simplification.
+// Numbers inside them are literals and fractions are quotients, so they read the same in every number mode
+use crate::bigint::BigInt;
+use crate::error::CalcError;
+use crate::expression::Expr;
+use crate::operators::Operators;
+use crate::rational::Rational;
+use crate::value::Value;
+
+// Operators that keep an expression of x unevaluated, the functions that do have a derivative rule in the registry
+pub(crate) const SYMBOLIC_OPERATORS: &[&str] = &["+", "-", "*", "/", "^"];
+
+// Constant powers are only folded up to this exponent
+const MAX_FOLDED_EXPONENT: i64 = 64;
+
+pub(crate) fn symbol() -> Expr {
+    Expr::Constant("x".to_owned())
+}
+
+pub(crate) fn contains_symbol(expr: &Expr) -> bool {
+    match expr {
+        Expr::Constant(name) => name == "x",
+        Expr::Operation(_, operands) => operands.iter().any(contains_symbol),
+        _ => false,
+    }
+}
+
+// Expression of a value that takes part in an expression of x
+pub(crate) fn to_expr(value: &Value) -> Expr {
+    match value {
+        Value::Symbolic(expr) => expr.clone(),
+        Value::Exact(x) => rational(x),
+        _ => Expr::Number(value.to_string()),
+    }
+}
+
+// An operator applied to an expression of x and another operand, which stays unevaluated
+pub(crate) fn combine(token: &str, a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let unsupported = [a, b].into_iter().find(|value| value.is_array() || value.is_quantity());
+    match unsupported {
+        Some(value) => {
+            let message = format!("'{}' cannot combine an expression of x with {}", token, value);
+            Err(CalcError::DomainError(message))
+        }
+        None if SYMBOLIC_OPERATORS.contains(&token) => {
+            Ok(Value::Symbolic(Expr::operation(token, vec![to_expr(a), to_expr(b)])))
+        }
+        None => {
+            let expr = [a, b].into_iter().find(|value| value.is_symbolic()).unwrap();
+            Err(CalcError::DomainError(format!("'{}' is not defined for the expression {}", token, expr)))
+        }
+    }
+}
+
+fn number(n: i64) -> Expr {
+    Expr::Number(n.to_string())
+}
+
+fn rational(x: &Rational) -> Expr {
+    if x.is_integer() {
+        Expr::Number(x.to_string())
+    } else {
+        let parts = vec![Expr::Number(x.numerator().to_string()), Expr::Number(x.denominator().to_string())];
+        Expr::operation("/", parts)
+    }
+}
+
+// Exact value of an expression made of numbers, None as soon as x or an irrational part is involved
+fn constant(expr: &Expr) -> Option<Rational> {
+    match expr {
+        Expr::Number(text) => Rational::parse(text),
+        Expr::Operation(token, operands) if token == "/" => constant(&operands[0])?.div(&constant(&operands[1])?),
+        _ => None,
+    }
+}
+
+fn is_constant(expr: &Expr, n: i64) -> bool {
+    constant(expr) == Some(Rational::from_integer(BigInt::from(n)))
+}
+
+fn op(token: &str, operands: Vec<Expr>) -> Expr {
+    Expr::operation(token, operands)
+}
+
+fn product(a: Expr, b: Expr) -> Expr {
+    op("*", vec![a, b])
+}
+
+// Derivative with respect to x, None if a function has no rule in the registry. Angles in degrees add
+// the factor pi/180 to the trigonometric functions and 180/pi to their inverses
+pub(crate) fn derivative(expr: &Expr, degrees: bool) -> Option<Expr> {
+    let Expr::Operation(token, operands) = expr else {
+        return Some(number(i64::from(expr == &symbol())));
+    };
+    let u = operands.first()?;
+    let du = derivative(u, degrees)?;
+    let derived = match token.as_str() {
+        "+" | "-" => op(token, vec![du, derivative(&operands[1], degrees)?]),
+        "*" => {
+            let v = &operands[1];
+            op("+", vec![product(du, v.clone()), product(u.clone(), derivative(v, degrees)?)])
+        }
+        "/" => {
+            let v = &operands[1];
+            let numerator = op("-", vec![product(du, v.clone()), product(u.clone(), derivative(v, degrees)?)]);
+            op("/", vec![numerator, op("^", vec![v.clone(), number(2)])])
+        }
+        // Power rule for constant exponents, otherwise d(u^v) = u^v * (v' ln u + v u' / u)
+        "^" if !contains_symbol(&operands[1]) => {
+            let v = &operands[1];
+            let power = op("^", vec![u.clone(), op("-", vec![v.clone(), number(1)])]);
+            product(product(v.clone(), power), du)
+        }
+        "^" => {
+            let v = &operands[1];
+            let logarithm = product(derivative(v, degrees)?, op("ln", vec![u.clone()]));
+            let quotient = op("/", vec![product(v.clone(), du), u.clone()]);
+            product(expr.clone(), op("+", vec![logarithm, quotient]))
+        }
+        _ => Operators::builtin().get(token)?.derivative?(u, du, degrees),
+    };
+    Some(derived)
+}
+
+fn pi() -> Expr {
+    Expr::Constant("pi".to_owned())
+}
+
+fn angle(du: Expr, degrees: bool) -> Expr {
+    if degrees { product(du, op("/", vec![pi(), number(180)])) } else { du }
+}
+
+fn inverse_angle(du: Expr, degrees: bool) -> Expr {
+    if degrees { product(du, op("/", vec![number(180), pi()])) } else { du }
+}
+
+fn logarithm(u: &Expr, du: Expr, base: i64) -> Expr {
+    op("/", vec![du, product(u.clone(), op("ln", vec![number(base)]))])
+}
+
+// sqrt(1 - u^2) of asin and acos
+fn root(u: &Expr) -> Expr {
+    op("sqrt", vec![op("-", vec![number(1), op("^", vec![u.clone(), number(2)])])])
+}
+
+// Rules of the functions in the registry, from the operand u and its derivative du
+
+pub(crate) fn square_root_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    op("/", vec![du, product(number(2), op("sqrt", vec![u.clone()]))])
+}
+
+pub(crate) fn natural_logarithm_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    op("/", vec![du, u.clone()])
+}
+
+pub(crate) fn common_logarithm_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    logarithm(u, du, 10)
+}
+
+pub(crate) fn binary_logarithm_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    logarithm(u, du, 2)
+}
+
+pub(crate) fn exponential_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    product(op("exp", vec![u.clone()]), du)
+}
+
+// The sign of u, undefined at 0 just like the derivative
+pub(crate) fn absolute_value_derivative(u: &Expr, du: Expr, _: bool) -> Expr {
+    product(op("/", vec![u.clone(), op("abs", vec![u.clone()])]), du)
+}
+
+pub(crate) fn sine_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    product(op("cos", vec![u.clone()]), angle(du, degrees))
+}
+
+pub(crate) fn cosine_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    product(product(number(-1), op("sin", vec![u.clone()])), angle(du, degrees))
+}
+
+pub(crate) fn tangent_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    op("/", vec![angle(du, degrees), op("^", vec![op("cos", vec![u.clone()]), number(2)])])
+}
+
+pub(crate) fn arcsine_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    op("/", vec![inverse_angle(du, degrees), root(u)])
+}
+
+pub(crate) fn arccosine_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    op("/", vec![product(number(-1), inverse_angle(du, degrees)), root(u)])
+}
+
+pub(crate) fn arctangent_derivative(u: &Expr, du: Expr, degrees: bool) -> Expr {
+    op("/", vec![inverse_angle(du, degrees), op("+", vec![number(1), op("^", vec![u.clone(), number(2)])])])
+}
+
+// Folds constant parts and removes neutral elements, bottom up
+pub(crate) fn simplify(expr: &Expr) -> Expr {
+    let Expr::Operation(token, operands) = expr else {
+        return expr.clone();
+    };
+    let operands: Vec<Expr> = operands.iter().map(simplify).collect();
+    if let Some(value) = fold(token, &operands) {
+        return rational(&value);
+    }
+    let a = operands[0].clone();
+    let Some(b) = operands.get(1).cloned() else {
+        return simplify_function(token, a);
+    };
+    match token.as_str() {
+        "+" | "-" => collect_terms(&op(token, vec![a, b])),
+        "*" if is_constant(&a, 0) || is_constant(&b, 0) => number(0),
+        "*" if is_constant(&a, 1) => b,
+        "*" if is_constant(&b, 1) => a,
+        "*" if a == b => op("^", vec![a, number(2)]),
+        // Constant factors move to the front and are multiplied together
+        "*" if constant(&b).is_some() => simplify(&op("*", vec![b, a])),
+        "*" => match (constant(&a), &b) {
+            (Some(c), Expr::Operation(inner, factors)) if inner == "*" && constant(&factors[0]).is_some() => {
+                let c = c.mul(&constant(&factors[0]).unwrap());
+                simplify(&op("*", vec![rational(&c), factors[1].clone()]))
+            }
+            _ => op("*", vec![a, b]),
+        },
+        "/" if is_constant(&a, 0) => number(0),
+        "/" if is_constant(&b, 1) => a,
+        "/" if a == b => number(1),
+        "^" if is_constant(&b, 0) || is_constant(&a, 1) => number(1),
+        "^" if is_constant(&b, 1) => a,
+        _ => op(token, vec![a, b]),
+    }
+}
+
+// Summand of a sum, split into its constant factor and the rest, which is None for a constant.
+// 'written' is the summand as it was typed and whether it is subtracted, it is kept as long as no
+// like term is added to it, so "2.5 * x" does not turn into "5 / 2 * x"
+struct Summand {
+    factor: Rational,
+    term: Option<Expr>,
+    written: Option<(bool, Expr)>,
+}
+
+fn add_summands(expr: &Expr, subtracted: bool, parts: &mut Vec<Summand>) {
+    if let Expr::Operation(token, operands) = expr {
+        if token == "+" || token == "-" {
+            add_summands(&operands[0], subtracted, parts);
+            add_summands(&operands[1], subtracted != (token == "-"), parts);
+            return;
+        }
+    }
+    let one = Rational::from_integer(BigInt::one());
+    let (factor, term) = match (constant(expr), expr) {
+        (Some(c), _) => (c, None),
+        (None, Expr::Operation(token, factors)) if token == "*" && constant(&factors[0]).is_some() => {
+            (constant(&factors[0]).unwrap(), Some(factors[1].clone()))
+        }
+        _ => (one, Some(expr.clone())),
+    };
+    let factor = if subtracted { Rational::from_integer(BigInt::zero()).sub(&factor) } else { factor };
+    match parts.iter_mut().find(|summand| summand.term == term) {
+        Some(summand) => {
+            summand.factor = summand.factor.add(&factor);
+            summand.written = None;
+        }
+        None => parts.push(Summand { factor, term, written: Some((subtracted, expr.clone())) }),
+    }
+}
+
+// Adds up like terms, "2 * x + 3 * x" is "5 * x" and "x + 1 - x" is "1". The summands keep their order
+fn collect_terms(sum: &Expr) -> Expr {
+    let mut parts = Vec::new();
+    add_summands(sum, false, &mut parts);
+    let scaled = |factor: &Rational, term: Option<Expr>| match term {
+        None => rational(factor),
+        Some(term) if is_constant(&rational(factor), 1) => term,
+        Some(term) => op("*", vec![rational(factor), term]),
+    };
+    let mut result: Option<Expr> = None;
+    for summand in parts.into_iter().filter(|summand| !summand.factor.is_zero()) {
+        result = Some(match (result, summand.written) {
+            (None, Some((false, written))) => written,
+            (None, _) => scaled(&summand.factor, summand.term),
+            (Some(sum), Some((subtracted, written))) => op(if subtracted { "-" } else { "+" }, vec![sum, written]),
+            (Some(sum), None) => {
+                let token = if summand.factor.is_negative() { "-" } else { "+" };
+                op(token, vec![sum, scaled(&summand.factor.abs(), summand.term)])
+            }
+        });
+    }
+    result.unwrap_or_else(|| number(0))
+}
+
+fn simplify_function(token: &str, u: Expr) -> Expr {
+    match token {
+        "ln" | "log" | "log2" if is_constant(&u, 1) => number(0),
+        "exp" if is_constant(&u, 0) => number(1),
+        "cos" if is_constant(&u, 0) => number(1),
+        "sqrt" | "abs" | "sin" | "tan" | "asin" | "atan" if is_constant(&u, 0) => number(0),
+        "sqrt" if is_constant(&u, 1) => number(1),
+        "ln" => match &u {
+            Expr::Operation(inner, operands) if inner == "exp" => operands[0].clone(),
+            _ => op(token, vec![u]),
+        },
+        _ => op(token, vec![u]),
+    }
+}
+
+// Exact value of an operator on constant operands, powers only for integer exponents
+fn fold(token: &str, operands: &[Expr]) -> Option<Rational> {
+    if operands.len() != 2 {
+        return None;
+    }
+    let (a, b) = (constant(&operands[0])?, constant(&operands[1])?);
+    match token {
+        "+" => Some(a.add(&b)),
+        "-" => Some(a.sub(&b)),
+        "*" => Some(a.mul(&b)),
+        "/" => a.div(&b),
+        "^" if b.is_integer() => {
+            let exponent = b.numerator().to_string().parse::<i64>().ok()?;
+            if exponent.abs() > MAX_FOLDED_EXPONENT {
+                return None;
+            }
+            a.pow(exponent)
+        }
+        _ => None,
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(tokens: &str) -> Expr {
+        let tokens: Vec<&str> = tokens.split_whitespace().collect();
+        Expr::from_rpn(&tokens).unwrap()
+    }
+
+    fn simplified(tokens: &str) -> String {
+        simplify(&parse(tokens)).to_infix()
+    }
+
+    fn derived(tokens: &str) -> String {
+        simplify(&derivative(&parse(tokens), false).unwrap()).to_infix()
+    }
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(simplified("x 0 + 1 *"), "x");
+        assert_eq!(simplified("x x -"), "0");
+        assert_eq!(simplified("x x +"), "2 * x");
+        assert_eq!(simplified("x x *"), "x ^ 2");
+        assert_eq!(simplified("x 3 * 2 *"), "6 * x");
+        assert_eq!(simplified("1 2 / 1 3 / + x *"), "5 / 6 * x");
+        assert_eq!(simplified("x 2 4 / ^"), "x ^ (1 / 2)");
+        assert_eq!(simplified("x 1 ^ 0 x / +"), "x");
+        assert_eq!(simplified("x exp ln 0 sin +"), "x");
+        assert_eq!(simplified("2 10 ^ x +"), "1024 + x");
+        assert_eq!(simplified("1 3 /"), "1 / 3");
+        assert_eq!(simplified("x 2.5 *"), "2.5 * x");
+        // Like terms are added up
+        assert_eq!(simplified("x 2 * 3 x * +"), "5 * x");
+        assert_eq!(simplified("x 2 * 1 + 3 x * + 4 -"), "5 * x - 3");
+        assert_eq!(simplified("x 1 + x -"), "1");
+        assert_eq!(simplified("x 2 * x 3 * -"), "-1 * x");
+        assert_eq!(simplified("x sin 2 * x cos + x sin +"), "3 * sin(x) + cos(x)");
+        assert_eq!(simplified("x 2.5 * 1 - x ln +"), "2.5 * x - 1 + ln(x)");
+    }
+
+    #[test]
+    fn test_derivative() {
+        assert_eq!(derived("x 3 ^"), "3 * x ^ 2");
+        assert_eq!(derived("x 2 ^ 3 x * + 1 +"), "2 * x + 3");
+        assert_eq!(derived("x sin x *"), "cos(x) * x + sin(x)");
+        assert_eq!(derived("1 x /"), "-1 / x ^ 2");
+        assert_eq!(derived("x 2 * exp"), "2 * exp(2 * x)");
+        assert_eq!(derived("x ln"), "1 / x");
+        assert_eq!(derived("x cos"), "-1 * sin(x)");
+        assert_eq!(derived("x sqrt"), "1 / (2 * sqrt(x))");
+        assert_eq!(derived("2 x ^"), "2 ^ x * ln(2)");
+        assert_eq!(derived("5"), "0");
+        assert_eq!(derived("x log"), "1 / (x * ln(10))");
+        assert_eq!(derived("x log2"), "1 / (x * ln(2))");
+        assert_eq!(derived("x abs"), "x / abs(x)");
+        assert_eq!(derived("x asin"), "1 / sqrt(1 - x ^ 2)");
+        assert_eq!(derived("x acos"), "-1 / sqrt(1 - x ^ 2)");
+        assert_eq!(derived("x 2 * atan"), "2 / (1 + (2 * x) ^ 2)");
+        let degrees = simplify(&derivative(&parse("x sin"), true).unwrap());
+        assert_eq!(degrees.to_infix(), "cos(x) * (pi / 180)");
+        let degrees = simplify(&derivative(&parse("x atan"), true).unwrap());
+        assert_eq!(degrees.to_infix(), "180 / pi / (1 + x ^ 2)");
+        // Functions without a rule cannot be derived
+        assert_eq!(derivative(&parse("x floor"), false), None);
+    }
+}
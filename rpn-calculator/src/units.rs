@@ -0,0 +1,310 @@
+// Physical units for quantities like "3 m" or "2.5 km/h". Every unit symbol has an exact factor
+// to the SI base units and a dimension, so conversions between units of the same dimension are exact
+use std::fmt;
+
+use crate::calculator::RPNCalculator;
+use crate::error::CalcError;
+use crate::rational::Rational;
+use crate::value::Value;
+
+// Exponents of the SI base dimensions: length, mass, time, current, temperature, amount, luminosity
+type Dimension = [i32; 7];
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+
+// Symbol, factor to the SI base units and dimension
+const UNITS: &[(&str, &str, Dimension)] = &[
+    ("m", "1", LENGTH),
+    ("km", "1000", LENGTH),
+    ("cm", "0.01", LENGTH),
+    ("mm", "0.001", LENGTH),
+    ("in", "0.0254", LENGTH),
+    ("ft", "0.3048", LENGTH),
+    ("mi", "1609.344", LENGTH),
+    ("kg", "1", MASS),
+    ("g", "0.001", MASS),
+    ("t", "1000", MASS),
+    ("lb", "0.45359237", MASS),
+    ("s", "1", TIME),
+    ("ms", "0.001", TIME),
+    ("h", "3600", TIME),
+    ("A", "1", [0, 0, 0, 1, 0, 0, 0]),
+    ("K", "1", [0, 0, 0, 0, 1, 0, 0]),
+    ("mol", "1", [0, 0, 0, 0, 0, 1, 0]),
+    ("cd", "1", [0, 0, 0, 0, 0, 0, 1]),
+    ("Hz", "1", [0, 0, -1, 0, 0, 0, 0]),
+    ("N", "1", [1, 1, -2, 0, 0, 0, 0]),
+    ("J", "1", [2, 1, -2, 0, 0, 0, 0]),
+    ("W", "1", [2, 1, -3, 0, 0, 0, 0]),
+    ("Pa", "1", [-1, 1, -2, 0, 0, 0, 0]),
+    ("C", "1", [0, 0, 1, 1, 0, 0, 0]),
+    ("V", "1", [2, 1, -3, -1, 0, 0, 0]),
+    ("L", "0.001", [3, 0, 0, 0, 0, 0, 0]),
+];
+
+// Units that are only accepted as the target of 'to' and '->', on its own 'min' is the minimum operator
+const CONVERSION_UNITS: &[(&str, &str, Dimension)] = &[("min", "60", TIME)];
+
+// Product of unit symbols with integer powers in the order they were combined, e.g. km^1 h^-1.
+// A symbol appears at most once and never with the power zero
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unit {
+    powers: Vec<(String, i32)>,
+}
+
+fn lookup(symbol: &str) -> Option<&'static (&'static str, &'static str, Dimension)> {
+    UNITS.iter().chain(CONVERSION_UNITS).find(|(name, _, _)| *name == symbol)
+}
+
+// Every unit symbol, for completion
+pub(crate) fn symbols() -> impl Iterator<Item = &'static str> {
+    UNITS.iter().map(|(symbol, _, _)| *symbol)
+}
+
+impl Unit {
+    // Parses "m", "m^2", "kg*m/s^2" and "J/kg/K", everything after a '/' is in the denominator
+    pub fn parse(text: &str) -> Option<Unit> {
+        Unit::parse_with(text, UNITS)
+    }
+
+    // Like 'parse', but also with the conversion only units, e.g. "min" in 'to min'
+    pub fn parse_target(text: &str) -> Option<Unit> {
+        Unit::parse_with(text, &[UNITS, CONVERSION_UNITS].concat())
+    }
+
+    fn parse_with(text: &str, units: &[(&str, &str, Dimension)]) -> Option<Unit> {
+        let mut unit = Unit { powers: Vec::new() };
+        for (index, part) in text.split('/').enumerate() {
+            let sign = if index == 0 { 1 } else { -1 };
+            for factor in part.split('*') {
+                let (symbol, power) = match factor.split_once('^') {
+                    Some((symbol, power)) => (symbol, power.parse::<i32>().ok()?),
+                    None => (factor, 1),
+                };
+                units.iter().find(|(name, _, _)| *name == symbol)?;
+                unit = unit.combine(&Unit { powers: vec![(symbol.to_owned(), power)] }, sign);
+            }
+        }
+        // Units that cancel completely like "m/m" are not units
+        if unit.powers.is_empty() { None } else { Some(unit) }
+    }
+
+    // Product for sign 1 and quotient for sign -1, powers of equal symbols are added
+    fn combine(&self, other: &Unit, sign: i32) -> Unit {
+        let mut powers = self.powers.clone();
+        for (symbol, power) in &other.powers {
+            match powers.iter_mut().find(|(existing, _)| existing == symbol) {
+                Some((_, existing)) => *existing += sign * power,
+                None => powers.push((symbol.clone(), sign * power)),
+            }
+        }
+        powers.retain(|(_, power)| *power != 0);
+        Unit { powers }
+    }
+
+    fn pow(&self, exponent: i32) -> Unit {
+        let powers = self.powers.iter().map(|(symbol, power)| (symbol.clone(), power * exponent)).collect();
+        Unit { powers }
+    }
+
+    fn factor(&self) -> Rational {
+        self.powers.iter().fold(Rational::parse("1").unwrap(), |factor, (symbol, power)| {
+            let (_, symbol_factor, _) = lookup(symbol).unwrap();
+            factor.mul(&Rational::parse(symbol_factor).unwrap().pow(*power as i64).unwrap())
+        })
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = [0; 7];
+        for (symbol, power) in &self.powers {
+            let (_, _, base) = lookup(symbol).unwrap();
+            for (total, exponent) in dimension.iter_mut().zip(base) {
+                *total += exponent * power;
+            }
+        }
+        dimension
+    }
+
+    // Typeset as a fraction of upright symbols
+    pub fn to_latex(&self) -> String {
+        let part = |sign: i32| {
+            let symbols: Vec<String> = self
+                .powers
+                .iter()
+                .filter(|(_, power)| power.signum() == sign)
+                .map(|(symbol, power)| match power.abs() {
+                    1 => format!(r"\mathrm{{{}}}", symbol),
+                    power => format!(r"\mathrm{{{}}}^{{{}}}", symbol, power),
+                })
+                .collect();
+            symbols.join(r" \cdot ")
+        };
+        match (part(1), part(-1)) {
+            (numerator, denominator) if denominator.is_empty() => numerator,
+            (numerator, denominator) if numerator.is_empty() => format!(r"\frac{{1}}{{{}}}", denominator),
+            (numerator, denominator) => format!(r"\frac{{{}}}{{{}}}", numerator, denominator),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = |symbol: &str, power: i32| match power {
+            1 => symbol.to_owned(),
+            _ => format!("{}^{}", symbol, power),
+        };
+        let numerator: Vec<String> = self
+            .powers
+            .iter()
+            .filter(|(_, power)| *power > 0)
+            .map(|(name, power)| symbol(name, *power))
+            .collect();
+        let mut text = if numerator.is_empty() { "1".to_owned() } else { numerator.join("*") };
+        for (name, power) in self.powers.iter().filter(|(_, power)| *power < 0) {
+            text.push('/');
+            text.push_str(&symbol(name, -power));
+        }
+        write!(f, "{}", text)
+    }
+}
+
+// A number together with its unit, a product without dimension like km/m becomes a plain number
+pub(crate) fn quantity(magnitude: Value, unit: Unit) -> Result<Value, CalcError> {
+    if unit.dimension() != [0; 7] {
+        return Ok(Value::Quantity(Box::new(magnitude), unit));
+    }
+    let factor = Value::Exact(unit.factor());
+    RPNCalculator::combine("*", &magnitude, &factor)
+}
+
+// Magnitude and unit of a value, plain numbers have no unit
+fn parts(value: &Value) -> (&Value, Unit) {
+    match value {
+        Value::Quantity(magnitude, unit) => (magnitude, unit.clone()),
+        _ => (value, Unit { powers: Vec::new() }),
+    }
+}
+
+// Factor that converts a magnitude in one unit into a magnitude in the other unit
+fn conversion(from: &Unit, to: &Unit) -> Option<Value> {
+    if from.dimension() != to.dimension() {
+        return None;
+    }
+    Some(Value::Exact(from.factor().div(&to.factor()).unwrap()))
+}
+
+fn incompatible(a: &Unit, b: &Unit) -> CalcError {
+    CalcError::IncompatibleUnits(a.to_string(), b.to_string())
+}
+
+// Arithmetic as soon as one operand has a unit. Sums are given in the unit of the left operand
+pub(crate) fn combine(token: &str, a: &Value, b: &Value) -> Result<Value, CalcError> {
+    let (x, unit_a) = parts(a);
+    let (y, unit_b) = parts(b);
+    match token {
+        "+" | "-" => {
+            let factor = conversion(&unit_b, &unit_a).ok_or_else(|| incompatible(&unit_a, &unit_b))?;
+            let y = RPNCalculator::combine("*", y, &factor)?;
+            quantity(RPNCalculator::combine(token, x, &y)?, unit_a)
+        }
+        "*" => quantity(RPNCalculator::combine(token, x, y)?, unit_a.combine(&unit_b, 1)),
+        "/" => quantity(RPNCalculator::combine(token, x, y)?, unit_a.combine(&unit_b, -1)),
+        // Only integer powers keep the unit a product of symbols
+        "^" if unit_b.powers.is_empty() => {
+            let exponent = y.to_f64();
+            if exponent.fract() != 0.0 || exponent.abs() > i32::MAX as f64 {
+                return Err(CalcError::DomainError(format!("units can only be raised to integer powers, got {}", y)));
+            }
+            quantity(RPNCalculator::combine(token, x, y)?, unit_a.pow(exponent as i32))
+        }
+        "^" => Err(CalcError::DomainError(format!("the exponent {} has a unit", b))),
+        _ => {
+            let value = if a.is_quantity() { a } else { b };
+            Err(CalcError::DomainError(format!("'{}' is not defined for the quantity {}", token, value)))
+        }
+    }
+}
+
+// Gives a value the unit, a value that already has a unit gets the product of both
+pub(crate) fn attach(value: &Value, unit: &Unit) -> Result<Value, CalcError> {
+    let (magnitude, existing) = parts(value);
+    quantity(magnitude.clone(), existing.combine(unit, 1))
+}
+
+// The same quantity in another unit of the same dimension
+pub(crate) fn convert(value: &Value, unit: &Unit) -> Result<Value, CalcError> {
+    let (magnitude, existing) = parts(value);
+    let factor = conversion(&existing, unit).ok_or_else(|| incompatible(&existing, unit))?;
+    Ok(Value::Quantity(Box::new(RPNCalculator::combine("*", magnitude, &factor)?), unit.clone()))
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(text: &str) -> Unit {
+        Unit::parse(text).unwrap()
+    }
+
+    fn exact(text: &str) -> Value {
+        Value::Exact(Rational::parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(unit("kg*m/s^2").to_string(), "kg*m/s^2");
+        assert_eq!(unit("J/kg/K").to_string(), "J/kg/K");
+        assert_eq!(unit("J/kg*K"), unit("J/kg/K"));
+        assert_eq!(unit("m*m").to_string(), "m^2");
+        assert_eq!(unit("s^-1").to_string(), "1/s");
+        assert_eq!(Unit::parse("m/m"), None);
+        assert_eq!(Unit::parse("furlong"), None);
+        assert_eq!(Unit::parse("m^x"), None);
+        assert_eq!(unit("km/h").to_latex(), r"\frac{\mathrm{km}}{\mathrm{h}}");
+        assert_eq!(unit("m^2").to_latex(), r"\mathrm{m}^{2}");
+    }
+
+    #[test]
+    fn test_dimensions() {
+        assert_eq!(unit("N*m").dimension(), unit("J").dimension());
+        assert_eq!(unit("km/h").factor(), Rational::parse("5/18").unwrap());
+        assert_eq!(unit("L").factor(), unit("cm^3").factor().mul(&Rational::parse("1000").unwrap()));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let length = attach(&exact("3"), &unit("km")).unwrap();
+        let time = attach(&exact("2"), &unit("h")).unwrap();
+        assert_eq!(combine("/", &length, &time).unwrap().to_string(), "3/2 km/h");
+        let sum = combine("+", &length, &attach(&exact("500"), &unit("m")).unwrap()).unwrap();
+        assert_eq!(sum.to_string(), "7/2 km");
+        assert_eq!(combine("/", &sum, &attach(&exact("1"), &unit("m")).unwrap()), Ok(exact("3500")));
+        assert_eq!(combine("^", &length, &exact("2")).unwrap().to_string(), "9 km^2");
+        assert_eq!(
+            combine("+", &length, &time),
+            Err(CalcError::IncompatibleUnits("km".to_owned(), "h".to_owned()))
+        );
+        assert_eq!(
+            combine("-", &length, &exact("1")),
+            Err(CalcError::IncompatibleUnits("km".to_owned(), "1".to_owned()))
+        );
+        assert!(combine("^", &length, &exact("1/2")).is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let speed = attach(&exact("10"), &unit("m/s")).unwrap();
+        assert_eq!(convert(&speed, &unit("km/h")).unwrap().to_string(), "36 km/h");
+        let force = attach(&exact("2"), &unit("kg*m/s^2")).unwrap();
+        assert_eq!(convert(&force, &unit("N")).unwrap().to_string(), "2 N");
+        assert!(convert(&speed, &unit("km")).is_err());
+        // Minutes are only a conversion target
+        assert_eq!(Unit::parse("min"), None);
+        let minutes = Unit::parse_target("km/min").unwrap();
+        assert_eq!(convert(&speed, &minutes).unwrap().to_string(), "3/5 km/min");
+    }
+}
@@ -0,0 +1,109 @@
+// Library for implementing the display
+use std::fmt;
+
+use crate::complex::Complex;
+use crate::expression::Expr;
+use crate::matrix::{self, Array};
+use crate::rational::Rational;
+use crate::units::Unit;
+
+// A single entry on the calculator stack, exact values only become floats through irrational functions
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Float(f64),
+    Exact(Rational),
+    Complex(Complex),
+    Vector(Vec<Value>),
+    // Rows of equal length
+    Matrix(Vec<Vec<Value>>),
+    // Number with a physical unit like "3 km"
+    Quantity(Box<Value>, Unit),
+    // Unevaluated expression of the symbolic variable x
+    Symbolic(Expr),
+}
+
+impl Value {
+    // Complex numbers have no real value unless their imaginary part is zero, vectors, matrices,
+    // quantities and expressions of x never
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Float(value) => *value,
+            Value::Exact(value) => value.to_f64(),
+            Value::Complex(value) if value.im == 0.0 => value.re,
+            Value::Complex(_) | Value::Vector(_) | Value::Matrix(_) | Value::Quantity(_, _) | Value::Symbolic(_) => {
+                f64::NAN
+            }
+        }
+    }
+
+    pub fn to_complex(&self) -> Complex {
+        match self {
+            Value::Complex(value) => *value,
+            _ => Complex::from_real(self.to_f64()),
+        }
+    }
+
+    // Results without an imaginary part are real numbers again
+    pub fn from_complex(value: Complex) -> Value {
+        if value.im == 0.0 { Value::Float(value.re) } else { Value::Complex(value) }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Value::Exact(_))
+    }
+
+    pub fn is_complex(&self) -> bool {
+        matches!(self, Value::Complex(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Value::Vector(_) | Value::Matrix(_))
+    }
+
+    pub fn is_quantity(&self) -> bool {
+        matches!(self, Value::Quantity(_, _))
+    }
+
+    pub fn is_symbolic(&self) -> bool {
+        matches!(self, Value::Symbolic(_))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<Complex> for Value {
+    fn from(value: Complex) -> Self {
+        Value::Complex(value)
+    }
+}
+
+impl From<Rational> for Value {
+    fn from(value: Rational) -> Self {
+        Value::Exact(value)
+    }
+}
+
+// Compares the numeric value, so exact and float values can be checked against plain numbers
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        self.to_f64() == *other
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Exact(value) => write!(f, "{}", value),
+            Value::Complex(value) => write!(f, "{}", value),
+            Value::Vector(elements) => write!(f, "{}", matrix::format_array(&Array::Vector(elements.to_vec()), " ")),
+            Value::Matrix(rows) => write!(f, "{}", matrix::format_array(&Array::Matrix(rows.to_vec()), " ")),
+            Value::Quantity(magnitude, unit) => write!(f, "{} {}", magnitude, unit),
+            Value::Symbolic(expr) => write!(f, "{}", expr.to_infix()),
+        }
+    }
+}
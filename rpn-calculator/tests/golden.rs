@@ -0,0 +1,72 @@
+// Golden-file tests of the interactive session: every 'tests/golden/<name>.rpn' is typed into
+// 'rpn-calculator --repl' line by line and the output has to match '<name>.out' exactly.
+// After an intended change of the output, run with UPDATE_GOLDEN=1 to rewrite the .out files
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+fn run_session(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpn-calculator"))
+        .arg("--repl")
+        // Without a home directory there is no history file, so earlier sessions cannot change the output
+        .env_remove("HOME")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("cannot start the calculator");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    let mut scripts: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rpn"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+// The first line that differs, with both versions
+fn difference(expected: &str, actual: &str) -> String {
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (expected, actual) if expected != actual => {
+                return format!("line {}:\n  expected: {:?}\n  actual:   {:?}", number, expected, actual);
+            }
+            _ => {}
+        }
+    }
+    "only the line endings differ".to_owned()
+}
+
+#[test]
+fn test_golden_sessions() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let scripts = scripts();
+    assert!(!scripts.is_empty(), "no scripts in tests/golden");
+
+    let mut failures = Vec::new();
+    for script in &scripts {
+        let actual = run_session(&fs::read_to_string(script).unwrap());
+        let golden = script.with_extension("out");
+        if update {
+            fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&golden).unwrap_or_default();
+        if actual != expected {
+            failures.push(format!("{}, {}", golden.display(), difference(&expected, &actual)));
+        }
+    }
+    assert!(failures.is_empty(), "output differs from the golden file\n{}", failures.join("\n"));
+}
@@ -0,0 +1,89 @@
+// Property tests: random RPN programs are evaluated, then the infix reconstruction of the result
+// is parsed and evaluated again and has to give the same value. The seeds are fixed, so every
+// failure can be reproduced
+use rpn_calculator::RPNCalculator;
+
+const NUMBERS: &[&str] = &["0", "1", "2", "3", "7", "12", "0.5", "2.25", "-3", "-0.5"];
+const BINARY: &[&str] = &["+", "-", "*", "/", "^", "mod", "min", "max", "atan2"];
+const UNARY: &[&str] = &["sqrt", "log", "abs", "!", "ln", "exp", "sin", "cos", "atan", "floor", "round"];
+
+// Random programs per number mode
+const CASES: usize = 500;
+
+// xorshift64*, good enough to pick tokens and the same on every platform
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize % n
+    }
+
+    fn pick(&mut self, tokens: &[&'static str]) -> &'static str {
+        tokens[self.below(tokens.len())]
+    }
+}
+
+// A program that leaves exactly one value on the stack, stack words included
+fn program(random: &mut Random) -> Vec<&'static str> {
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    for _ in 0..1 + random.below(10) {
+        match random.below(10) {
+            4..=6 if depth >= 2 => {
+                tokens.push(random.pick(BINARY));
+                depth -= 1;
+            }
+            7 | 8 if depth >= 1 => tokens.push(random.pick(UNARY)),
+            9 if depth >= 2 => tokens.push("swap"),
+            9 if depth == 1 => {
+                tokens.push("dup");
+                depth += 1;
+            }
+            _ => {
+                tokens.push(random.pick(NUMBERS));
+                depth += 1;
+            }
+        }
+    }
+    for _ in 1..depth {
+        tokens.push(random.pick(BINARY));
+    }
+    tokens
+}
+
+fn check_reparsed_infix(mode: &str, seed: u64) {
+    let mut random = Random(seed);
+    let mut checked = 0;
+    for _ in 0..CASES {
+        let line = format!("{} {}", mode, program(&mut random).join(" "));
+        let mut calc = RPNCalculator::new();
+        // Programs that divide by zero, overflow and the like are not of interest here
+        if calc.evaluate_line(&line).is_err() {
+            continue;
+        }
+        let infix = calc.infix();
+        let mut reparsed = RPNCalculator::new();
+        reparsed.evaluate_line(mode).unwrap();
+        if let Err(error) = reparsed.evaluate_infix(&infix) {
+            panic!("'{}' gives the infix '{}', which does not evaluate: {}", line, infix, error);
+        }
+        // Display strings, so NaN equals NaN
+        let (expected, actual) = (calc.get_result().unwrap().to_string(), reparsed.get_result().unwrap().to_string());
+        assert_eq!(actual, expected, "'{}' gives the infix '{}'", line, infix);
+        checked += 1;
+    }
+    assert!(checked > CASES / 2, "only {} of {} programs could be evaluated", checked, CASES);
+}
+
+#[test]
+fn test_infix_reconstruction_floats() {
+    check_reparsed_infix("float", 0x5eed_0001);
+}
+
+#[test]
+fn test_infix_reconstruction_exact() {
+    check_reparsed_infix("exact", 0x5eed_0002);
+}
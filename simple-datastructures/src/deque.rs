@@ -0,0 +1,208 @@
+use crate::stack::Stack;
+use crate::datastructure::Datastructure;
+
+pub struct Deque<T> {
+    front: Stack<T>, // Stack für das vordere Ende, oben liegt das erste Element
+    back: Stack<T>, // Stack für das hintere Ende, oben liegt das letzte Element
+}
+
+impl<T: PartialEq + std::fmt::Display> Deque<T> {
+    pub fn new() -> Self {
+        Deque {
+            front: Stack::new(),
+            back: Stack::new(),
+        }
+    }
+
+    // Fügt ein Element vorne in die Deque ein
+    pub fn push_front(&mut self, data: T) {
+        self.front.push(data);
+    }
+
+    // Fügt ein Element hinten in die Deque ein
+    pub fn push_back(&mut self, data: T) {
+        self.back.push(data);
+    }
+
+    // Entfernt das vorderste Element aus der Deque
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.front.is_empty() {
+            Self::balance(&mut self.back, &mut self.front);
+        }
+        self.front.pop()
+    }
+
+    // Entfernt das hinterste Element aus der Deque
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.back.is_empty() {
+            Self::balance(&mut self.front, &mut self.back);
+        }
+        self.back.pop()
+    }
+
+    // Gibt das vorderste Element zurück, ohne es zu entfernen
+    pub fn peek_front(&mut self) -> Option<&T> {
+        if self.front.is_empty() {
+            Self::balance(&mut self.back, &mut self.front);
+        }
+        self.front.peek()
+    }
+
+    // Gibt das hinterste Element zurück, ohne es zu entfernen
+    pub fn peek_back(&mut self) -> Option<&T> {
+        if self.back.is_empty() {
+            Self::balance(&mut self.front, &mut self.back);
+        }
+        self.back.peek()
+    }
+
+    // Verschiebt die untere Hälfte von "from" auf "to", damit das unterste Element von "from"
+    // oben auf "to" liegt. Anders als bei der Queue bleibt die obere Hälfte liegen, sonst würde
+    // abwechselndes Entfernen an beiden Enden jedes Mal alle Elemente umschichten
+    fn balance(from: &mut Stack<T>, to: &mut Stack<T>) {
+        // Von oben nach unten
+        let mut items = Vec::new();
+        while let Some(data) = from.pop() {
+            items.push(data);
+        }
+        let moved = items.split_off(items.len() / 2);
+
+        // Die obere Hälfte in der alten Reihenfolge zurücklegen
+        for data in items.into_iter().rev() {
+            from.push(data);
+        }
+        // Das unterste Element wird zuletzt gepusht und liegt damit oben
+        for data in moved {
+            to.push(data);
+        }
+    }
+}
+
+// Implementierung des Datastructure-Traits für Deque
+impl<T> Datastructure<T> for Deque<T> where T: PartialEq + ToString + std::fmt::Display {
+    // Gibt die Deque von vorne nach hinten als String zurück
+    fn to_string(&self) -> String {
+        let mut items = Vec::new();
+
+        // Zuerst die Elemente von front, von oben nach unten
+        let mut current = &self.front.head;
+        while let Some(node) = current {
+            items.push(node.data.to_string());
+            current = &node.next;
+        }
+
+        // Dann die Elemente von back, von unten nach oben
+        let mut back_items = Vec::new();
+        let mut current_back = &self.back.head;
+        while let Some(node) = current_back {
+            back_items.push(node.data.to_string());
+            current_back = &node.next;
+        }
+        back_items.reverse();
+        items.append(&mut back_items);
+
+        items.join(" -> ")
+    }
+
+    // Überprüft, ob die Deque leer ist
+    fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+
+    // Gibt die Größe der Deque zurück
+    fn size(&self) -> i32 {
+        self.front.size() + self.back.size()
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_deque() {
+        let deque: Deque<i32> = Deque::new();
+        assert!(deque.is_empty());
+        assert_eq!(deque.size(), 0);
+    }
+
+    #[test]
+    fn test_push_pop_front() {
+        let mut deque = Deque::new();
+        deque.push_front(10);
+        deque.push_front(20);
+        assert_eq!(deque.pop_front(), Some(20));
+        assert_eq!(deque.pop_front(), Some(10));
+        assert_eq!(deque.pop_front(), None);
+    }
+
+    #[test]
+    fn test_push_back_pop_front() {
+        let mut deque = Deque::new();
+        deque.push_back(1);
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_back(4);
+
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_front(), Some(2));
+        deque.push_front(5);
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.to_string(), "5 -> 3");
+        assert_eq!(deque.size(), 2);
+    }
+
+    #[test]
+    fn test_pop_back_after_push_front() {
+        let mut deque = Deque::new();
+        deque.push_front(3);
+        deque.push_front(2);
+        deque.push_front(1);
+
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_back(), Some(2));
+        assert_eq!(deque.pop_back(), Some(1));
+        assert_eq!(deque.pop_back(), None);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut deque = Deque::new();
+        assert_eq!(deque.peek_front(), None);
+        assert_eq!(deque.peek_back(), None);
+
+        deque.push_back(30);
+        deque.push_back(40);
+        assert_eq!(deque.peek_front(), Some(&30));
+        assert_eq!(deque.peek_back(), Some(&40));
+        assert_eq!(deque.size(), 2);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut deque = Deque::new();
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        assert_eq!(deque.to_string(), "1 -> 2 -> 3");
+    }
+
+    #[test]
+    fn test_equals() {
+        let mut deque1 = Deque::new();
+        let mut deque2 = Deque::new();
+
+        deque1.push_back(1);
+        deque1.push_back(2);
+        deque2.push_front(2);
+        deque2.push_front(1);
+
+        assert!(deque1.equals(&deque2));
+
+        deque2.pop_back();
+        assert!(!deque1.equals(&deque2));
+    }
+}
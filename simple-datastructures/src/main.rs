@@ -4,6 +4,10 @@ mod queue;
 use queue::Queue;
 mod list;
 use list::LinkedList;
+mod deque;
+use deque::Deque;
+mod priority_queue;
+use priority_queue::PriorityQueue;
 mod datastructure;
 use crate::datastructure::Datastructure;
 
@@ -172,6 +176,46 @@ fn main() {
     println!("get(25) erster Aufruf: {}", list.get(&25));
     println!("get(45) zweiter Aufruf: {}", list.get(&45));
 
+    println!("===================================");
+    println!("Deque");
+    println!("===================================");
+
+    let mut deque = Deque::new();
+    deque.push_back(2);
+    deque.push_back(3);
+    deque.push_front(1);
+    println!("Deque: {}", deque.to_string()); // Output: 1 -> 2 -> 3
+
+    println!("pop_front: {:?}", deque.pop_front()); // Output: Some(1)
+    println!("pop_back: {:?}", deque.pop_back()); // Output: Some(3)
+    deque.push_front(0);
+    println!("Deque: {}", deque.to_string()); // Output: 0 -> 2
+
+    // Deque-Methoden
+    println!("Deque Größe: {}", deque.size());
+    println!("Ist die Deque leer? {}", deque.is_empty());
+    println!("Vorderstes Element: {:?}", deque.peek_front());
+    println!("Hinterstes Element: {:?}", deque.peek_back());
+
+    println!("===================================");
+    println!("PriorityQueue");
+    println!("===================================");
+
+    // Max-Heap: das größte Element zuerst
+    let mut priority_queue = PriorityQueue::new();
+    priority_queue.push_all(vec![5, 1, 8, 3]);
+    println!("PriorityQueue: {}", priority_queue.to_string()); // Output: 8 -> 5 -> 3 -> 1
+    println!("Pop: {:?}", priority_queue.pop()); // Output: Some(8)
+    println!("Was ist das oberste Element? {:?}", priority_queue.peek()); // Output: Some(5)
+
+    // Eigene Vergleichsfunktion: Min-Heap, z.B. für Aufgaben mit der frühesten Startzeit zuerst
+    let mut min_queue = PriorityQueue::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    min_queue.push_all(vec![30, 10, 20]);
+    println!("Min-Heap: {}", min_queue.to_string()); // Output: 10 -> 20 -> 30
+    println!("Pop: {:?}", min_queue.pop()); // Output: Some(10)
+    println!("PriorityQueue Größe: {}", min_queue.size());
+    println!("Ist die PriorityQueue leer? {}", min_queue.is_empty());
+
     // For no unused warnings
     queue.size();
     stack.peek();
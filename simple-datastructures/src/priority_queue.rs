@@ -0,0 +1,192 @@
+use std::cmp::Ordering;
+
+use crate::datastructure::Datastructure;
+
+// Vergleichsfunktion, das größere Element hat die höhere Priorität
+type Comparator<T> = Box<dyn Fn(&T, &T) -> Ordering>;
+
+pub struct PriorityQueue<T> {
+    heap: Vec<T>, // Binärer Heap, die Kinder von Index i liegen bei 2i + 1 und 2i + 2
+    compare: Comparator<T>,
+}
+
+impl<T: Ord + std::fmt::Display> PriorityQueue<T> {
+    // Max-Heap: das größte Element kommt zuerst
+    pub fn new() -> Self {
+        PriorityQueue::with_comparator(|a: &T, b: &T| a.cmp(b))
+    }
+}
+
+impl<T: std::fmt::Display> PriorityQueue<T> {
+    // Mit eigener Vergleichsfunktion, z.B. |a, b| b.cmp(a) für einen Min-Heap
+    pub fn with_comparator<F>(compare: F) -> Self
+    where
+        F: Fn(&T, &T) -> Ordering + 'static,
+    {
+        PriorityQueue {
+            heap: Vec::new(),
+            compare: Box::new(compare),
+        }
+    }
+
+    // Fügt ein Element ein und lässt es nach oben wandern
+    pub fn push(&mut self, data: T) {
+        self.heap.push(data);
+        self.sift_up(self.heap.len() - 1);
+    }
+
+    // Fügt mehrere Elemente ein
+    pub fn push_all<I: IntoIterator<Item = T>>(&mut self, data: I) {
+        for item in data {
+            self.push(item);
+        }
+    }
+
+    // Entfernt das Element mit der höchsten Priorität und gibt es zurück
+    pub fn pop(&mut self) -> Option<T> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        // Das letzte Element ersetzt die Wurzel und wandert nach unten
+        let last = self.heap.len() - 1;
+        self.heap.swap(0, last);
+        let data = self.heap.pop();
+        self.sift_down(0);
+        data
+    }
+
+    // Gibt das Element mit der höchsten Priorität zurück, ohne es zu entfernen
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first()
+    }
+
+    // Tauscht das Element so lange mit seinem Elternknoten, bis dieser nicht kleiner ist
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if (self.compare)(&self.heap[index], &self.heap[parent]) != Ordering::Greater {
+                break;
+            }
+            self.heap.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    // Tauscht das Element so lange mit dem größeren Kind, bis kein Kind größer ist
+    fn sift_down(&mut self, mut index: usize) {
+        loop {
+            let mut largest = index;
+            for child in [2 * index + 1, 2 * index + 2] {
+                if child >= self.heap.len() {
+                    continue;
+                }
+                if (self.compare)(&self.heap[child], &self.heap[largest]) == Ordering::Greater {
+                    largest = child;
+                }
+            }
+            if largest == index {
+                break;
+            }
+            self.heap.swap(index, largest);
+            index = largest;
+        }
+    }
+}
+
+// Implementierung des Datastructure-Traits für PriorityQueue
+impl<T> Datastructure<T> for PriorityQueue<T> where T: PartialEq + ToString + std::fmt::Display {
+    // Gibt die Elemente nach Priorität geordnet zurück, unabhängig vom Aufbau des Heaps
+    fn to_string(&self) -> String {
+        let mut sorted: Vec<&T> = self.heap.iter().collect();
+        sorted.sort_by(|a, b| (self.compare)(b, a));
+
+        let items: Vec<String> = sorted.iter().map(|data| data.to_string()).collect();
+        items.join(" -> ")
+    }
+
+    // Überprüft, ob die PriorityQueue leer ist
+    fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    // Gibt die Größe der PriorityQueue zurück
+    fn size(&self) -> i32 {
+        self.heap.len() as i32
+    }
+}
+
+// ------------------------------Testing--------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_priority_queue() {
+        let queue: PriorityQueue<i32> = PriorityQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.size(), 0);
+        assert_eq!(queue.peek(), None);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut queue = PriorityQueue::new();
+        queue.push_all(vec![5, 1, 8, 3, 9, 2]);
+        assert_eq!(queue.size(), 6);
+        assert_eq!(queue.peek(), Some(&9));
+
+        assert_eq!(queue.pop(), Some(9));
+        assert_eq!(queue.pop(), Some(8));
+        assert_eq!(queue.pop(), Some(5));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_min_heap() {
+        let mut queue = PriorityQueue::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        queue.push_all(vec![40, 10, 30, 20]);
+        assert_eq!(queue.pop(), Some(10));
+        assert_eq!(queue.pop(), Some(20));
+        assert_eq!(queue.peek(), Some(&30));
+    }
+
+    #[test]
+    fn test_comparator_by_key() {
+        // Aufgaben mit Priorität, die Länge des Namens bestimmt die Reihenfolge
+        let mut queue = PriorityQueue::with_comparator(|a: &String, b: &String| a.len().cmp(&b.len()));
+        queue.push("io".to_string());
+        queue.push("backup".to_string());
+        queue.push("mail".to_string());
+        assert_eq!(queue.pop(), Some("backup".to_string()));
+        assert_eq!(queue.pop(), Some("mail".to_string()));
+        assert_eq!(queue.pop(), Some("io".to_string()));
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut queue = PriorityQueue::new();
+        queue.push(2);
+        queue.push(7);
+        queue.push(4);
+        assert_eq!(queue.to_string(), "7 -> 4 -> 2");
+    }
+
+    #[test]
+    fn test_equals() {
+        let mut queue1 = PriorityQueue::new();
+        let mut queue2 = PriorityQueue::new();
+
+        queue1.push_all(vec![1, 2, 3]);
+        queue2.push_all(vec![3, 1, 2]);
+
+        assert!(queue1.equals(&queue2));
+
+        queue2.pop();
+        assert!(!queue1.equals(&queue2));
+    }
+}